@@ -0,0 +1,22 @@
+//! FADT (сигнатура "FACP"). Ядру из неё нужен только номер регистра века
+//! в CMOS: стандартного места у него нет.
+
+use super::{find_table, HEADER_SIZE};
+
+// Смещение поля CENTURY от начала таблицы
+const CENTURY: usize = 108;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Fadt {
+    /// Регистр века в CMOS, 0 — часы век не хранят.
+    pub century: u8,
+}
+
+impl Fadt {
+    pub fn load() -> Result<Fadt, &'static str> {
+        let table = find_table(b"FACP")?;
+        // Поле есть с ACPI 1.0, но короткие таблицы встречаются
+        let century = table.body().get(CENTURY - HEADER_SIZE).copied().unwrap_or(0);
+        Ok(Fadt { century })
+    }
+}
@@ -0,0 +1,149 @@
+//! MADT (сигнатура "APIC"): процессоры, контроллеры I/O APIC и то, как
+//! линии ISA подключены к их входам.
+
+use super::{find_table, read_u16, read_u32, read_u64};
+
+const ENTRY_LOCAL_APIC: u8 = 0;
+const ENTRY_IO_APIC: u8 = 1;
+const ENTRY_SOURCE_OVERRIDE: u8 = 2;
+const ENTRY_LOCAL_APIC_ADDRESS: u8 = 5;
+
+// Флаги MADT: кроме APIC есть и пара 8259
+const FLAG_PCAT_COMPAT: u32 = 1 << 0;
+const LOCAL_APIC_ENABLED: u32 = 1 << 0;
+
+pub const MAX_LOCAL_APICS: usize = 16;
+pub const MAX_IO_APICS: usize = 4;
+pub const MAX_OVERRIDES: usize = 16;
+
+#[derive(Debug, Clone, Copy)]
+pub struct LocalApic {
+    pub processor_id: u8,
+    pub apic_id: u8,
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct IoApic {
+    pub id: u8,
+    pub address: u64,
+    /// Первое глобальное прерывание (GSI), которое обслуживает контроллер.
+    pub gsi_base: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Polarity {
+    High,
+    Low,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trigger {
+    Edge,
+    Level,
+}
+
+/// Линия ISA `irq` подключена к `gsi`, а не к входу с тем же номером.
+#[derive(Debug, Clone, Copy)]
+pub struct SourceOverride {
+    pub irq: u8,
+    pub gsi: u32,
+    pub polarity: Polarity,
+    pub trigger: Trigger,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Madt {
+    pub local_apic_address: u64,
+    pub has_8259: bool,
+    pub local_apics: [Option<LocalApic>; MAX_LOCAL_APICS],
+    pub io_apics: [Option<IoApic>; MAX_IO_APICS],
+    pub overrides: [Option<SourceOverride>; MAX_OVERRIDES],
+}
+
+fn push<T>(slots: &mut [Option<T>], value: T) {
+    if let Some(slot) = slots.iter_mut().find(|slot| slot.is_none()) {
+        *slot = Some(value);
+    }
+}
+
+fn parse_override(entry: &[u8]) -> SourceOverride {
+    let flags = read_u16(entry, 8);
+    // 0 — как принято для шины: у ISA это фронт и высокий уровень
+    SourceOverride {
+        irq: entry[3],
+        gsi: read_u32(entry, 4),
+        polarity: if flags & 0x3 == 0x3 { Polarity::Low } else { Polarity::High },
+        trigger: if (flags >> 2) & 0x3 == 0x3 { Trigger::Level } else { Trigger::Edge },
+    }
+}
+
+impl Madt {
+    pub fn load() -> Result<Madt, &'static str> {
+        let table = find_table(b"APIC")?;
+        let body = table.body();
+        if body.len() < 8 {
+            return Err("MADT is too short");
+        }
+
+        let mut madt = Madt {
+            local_apic_address: read_u32(body, 0) as u64,
+            has_8259: read_u32(body, 4) & FLAG_PCAT_COMPAT != 0,
+            local_apics: [None; MAX_LOCAL_APICS],
+            io_apics: [None; MAX_IO_APICS],
+            overrides: [None; MAX_OVERRIDES],
+        };
+
+        let mut offset = 8;
+        while offset + 2 <= body.len() {
+            let (kind, len) = (body[offset], body[offset + 1] as usize);
+            if len < 2 || offset + len > body.len() {
+                break;
+            }
+            let entry = &body[offset..offset + len];
+            match kind {
+                ENTRY_LOCAL_APIC if len >= 8 => push(&mut madt.local_apics, LocalApic {
+                    processor_id: entry[2],
+                    apic_id: entry[3],
+                    enabled: read_u32(entry, 4) & LOCAL_APIC_ENABLED != 0,
+                }),
+                ENTRY_IO_APIC if len >= 12 => push(&mut madt.io_apics, IoApic {
+                    id: entry[2],
+                    address: read_u32(entry, 4) as u64,
+                    gsi_base: read_u32(entry, 8),
+                }),
+                ENTRY_SOURCE_OVERRIDE if len >= 10 => push(&mut madt.overrides, parse_override(entry)),
+                ENTRY_LOCAL_APIC_ADDRESS if len >= 12 => madt.local_apic_address = read_u64(entry, 4),
+                _ => {}
+            }
+            offset += len;
+        }
+        Ok(madt)
+    }
+
+    /// Куда подключена линия ISA `irq` и как она срабатывает.
+    pub fn route(&self, irq: u8) -> SourceOverride {
+        self.overrides
+            .iter()
+            .flatten()
+            .find(|o| o.irq == irq)
+            .copied()
+            .unwrap_or(SourceOverride {
+                irq,
+                gsi: irq as u32,
+                polarity: Polarity::High,
+                trigger: Trigger::Edge,
+            })
+    }
+
+    /// Контроллер, к которому подключено прерывание `gsi`, и номер его входа.
+    pub fn io_apic_for(&self, gsi: u32) -> Option<(usize, u32)> {
+        self.io_apics
+            .iter()
+            .enumerate()
+            .filter_map(|(index, io)| io.map(|io| (index, io)))
+            .filter(|(_, io)| gsi >= io.gsi_base)
+            .max_by_key(|(_, io)| io.gsi_base)
+            .map(|(index, io)| (index, gsi - io.gsi_base))
+    }
+}
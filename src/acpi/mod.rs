@@ -0,0 +1,207 @@
+//! Таблицы ACPI.
+//!
+//! Загрузчик кладёт копию RSDP в теги multiboot2. RSDP указывает на RSDT с
+//! 32-битными адресами остальных таблиц, а начиная с ACPI 2.0 — ещё и на XSDT
+//! с 64-битными. Таблицы только читаются: ниже `IDENTITY_LIMIT` они видны по
+//! физическому адресу, выше отображаются в своё окно. Это обычная память, так
+//! что отображение кэшируемое и только для чтения; каждая страница
+//! отображается один раз, а корневая таблица разбирается при первом поиске.
+
+pub mod fadt;
+pub mod madt;
+
+use x86_64::structures::paging::PageTableFlags;
+use x86_64::{PhysAddr, VirtAddr};
+use crate::memory::paging::{self, IDENTITY_LIMIT};
+use crate::multiboot;
+use crate::spin::SpinMutex;
+
+const RSDP_SIGNATURE: &[u8; 8] = b"RSD PTR ";
+const RSDP_V1_SIZE: usize = 20;
+const RSDP_V2_SIZE: usize = 36;
+
+/// Заголовок, общий для всех таблиц кроме RSDP.
+pub const HEADER_SIZE: usize = 36;
+
+const PAGE_SIZE: u64 = 4096;
+// Таблиц немного и они небольшие, MADT и FADT укладываются в страницу-две
+const WINDOW_SIZE: u64 = 1 << 20;
+const MAX_RANGES: usize = 32;
+
+/// Отображённый кусок физической памяти; границы выровнены на страницу.
+#[derive(Debug, Clone, Copy)]
+struct Range {
+    phys: u64,
+    len: u64,
+    virt: u64,
+}
+
+impl Range {
+    fn end(&self) -> u64 {
+        self.phys + self.len
+    }
+}
+
+struct Window {
+    /// 0 — окно ещё не зарезервировано.
+    start: u64,
+    used: u64,
+    ranges: [Option<Range>; MAX_RANGES],
+}
+
+static WINDOW: SpinMutex<Window> = SpinMutex::new(Window {
+    start: 0,
+    used: 0,
+    ranges: [None; MAX_RANGES],
+});
+
+/// Корневая таблица и размер адреса в ней, после первого поиска.
+static ROOT: SpinMutex<Option<(Table, usize)>> = SpinMutex::new(None);
+
+/// Таблица целиком, вместе с заголовком.
+#[derive(Debug, Clone, Copy)]
+pub struct Table {
+    pub bytes: &'static [u8],
+}
+
+impl Table {
+    pub fn signature(&self) -> &'static [u8] {
+        &self.bytes[0..4]
+    }
+
+    /// Данные после заголовка.
+    pub fn body(&self) -> &'static [u8] {
+        &self.bytes[HEADER_SIZE..]
+    }
+}
+
+pub fn read_u16(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+}
+
+pub fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    let mut value = [0; 4];
+    value.copy_from_slice(&bytes[offset..offset + 4]);
+    u32::from_le_bytes(value)
+}
+
+pub fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+    let mut value = [0; 8];
+    value.copy_from_slice(&bytes[offset..offset + 8]);
+    u64::from_le_bytes(value)
+}
+
+fn checksum_ok(bytes: &[u8]) -> bool {
+    bytes.iter().fold(0u8, |sum, &byte| sum.wrapping_add(byte)) == 0
+}
+
+impl Window {
+    /// Отображает страницы `[first, end)` в конец окна.
+    fn map_pages(&mut self, first: u64, end: u64) -> Result<u64, &'static str> {
+        if self.start == 0 {
+            self.start = paging::reserve_window(WINDOW_SIZE).map_err(|_| "no room for ACPI tables")?.as_u64();
+        }
+        if self.used + (end - first) > WINDOW_SIZE {
+            return Err("ACPI window is full");
+        }
+        let virt = self.start + self.used;
+        paging::map_range(VirtAddr::new(virt), PhysAddr::new(first), end - first, PageTableFlags::empty())
+            .map_err(|_| "cannot map ACPI table")?;
+        self.used += end - first;
+        Ok(virt)
+    }
+
+    fn map(&mut self, phys: u64, end: u64) -> Result<u64, &'static str> {
+        let first = phys & !(PAGE_SIZE - 1);
+        let end = end.next_multiple_of(PAGE_SIZE);
+        if let Some(range) = self.ranges.iter().flatten().find(|r| r.phys <= first && end <= r.end()) {
+            return Ok(range.virt + (phys - range.phys));
+        }
+
+        // Таблица длиннее своего заголовка: последний кусок лежит в конце
+        // окна, так что недостающие страницы просто дописываются за ним
+        let window_end = self.start + self.used;
+        let last = self.ranges.iter().rposition(Option::is_some);
+        if let Some(index) = last {
+            let range = self.ranges[index].unwrap();
+            if range.virt + range.len == window_end && range.phys <= first && first <= range.end() {
+                self.map_pages(range.end(), end)?;
+                self.ranges[index] = Some(Range { len: end - range.phys, ..range });
+                return Ok(range.virt + (phys - range.phys));
+            }
+        }
+
+        let slot = self.ranges.iter().position(Option::is_none).ok_or("too many ACPI tables")?;
+        let virt = self.map_pages(first, end)?;
+        self.ranges[slot] = Some(Range { phys: first, len: end - first, virt });
+        Ok(virt + (phys - first))
+    }
+}
+
+/// Делает `len` байт по физическому адресу `phys` доступными для чтения.
+fn map(phys: u64, len: usize) -> Result<&'static [u8], &'static str> {
+    let end = phys.checked_add(len as u64).ok_or("ACPI table address is out of range")?;
+    let addr = if end <= IDENTITY_LIMIT {
+        phys
+    } else {
+        WINDOW.lock().map(phys, end)?
+    };
+    Ok(unsafe { core::slice::from_raw_parts(addr as *const u8, len) })
+}
+
+fn load_table(phys: u64) -> Result<Table, &'static str> {
+    let header = map(phys, HEADER_SIZE)?;
+    let len = read_u32(header, 4) as usize;
+    if len < HEADER_SIZE {
+        return Err("ACPI table is too short");
+    }
+    let bytes = map(phys, len)?;
+    if !checksum_ok(bytes) {
+        return Err("ACPI table checksum mismatch");
+    }
+    Ok(Table { bytes })
+}
+
+/// Корневая таблица и размер адреса в ней: 8 для XSDT, 4 для RSDT.
+fn root_table() -> Result<(Table, usize), &'static str> {
+    let rsdp = multiboot::info().and_then(|info| info.rsdp()).ok_or("bootloader passed no RSDP")?;
+    let rsdp = unsafe { core::slice::from_raw_parts(rsdp as *const u8, RSDP_V1_SIZE) };
+    if &rsdp[0..8] != RSDP_SIGNATURE || !checksum_ok(rsdp) {
+        return Err("RSDP is corrupted");
+    }
+
+    if rsdp[15] >= 2 {
+        let rsdp = unsafe { core::slice::from_raw_parts(rsdp.as_ptr(), RSDP_V2_SIZE) };
+        let xsdt = read_u64(rsdp, 24);
+        if checksum_ok(rsdp) && xsdt != 0 {
+            return Ok((load_table(xsdt)?, 8));
+        }
+    }
+    Ok((load_table(read_u32(rsdp, 16) as u64)?, 4))
+}
+
+/// Ищет таблицу по сигнатуре, например `b"APIC"` для MADT.
+pub fn find_table(signature: &[u8; 4]) -> Result<Table, &'static str> {
+    let cached = *ROOT.lock();
+    let (root, entry_size) = match cached {
+        Some(root) => root,
+        None => {
+            let root = root_table()?;
+            *ROOT.lock() = Some(root);
+            root
+        }
+    };
+    for entry in root.body().chunks_exact(entry_size) {
+        let phys = match entry_size {
+            8 => read_u64(entry, 0),
+            _ => read_u32(entry, 0) as u64,
+        };
+        // Испорченная таблица не мешает найти остальные
+        if let Ok(table) = load_table(phys) {
+            if table.signature() == signature {
+                return Ok(table);
+            }
+        }
+    }
+    Err("ACPI table not found")
+}
@@ -0,0 +1,112 @@
+//! Список свободных блоков, упорядоченный по адресу.
+//!
+//! Размеры и адреса блоков кратны `BLOCK_ALIGN`, поэтому остаток после
+//! выделения либо пуст, либо вмещает заголовок свободного блока. Соседние
+//! свободные блоки при освобождении склеиваются.
+
+use core::ptr::null_mut;
+
+pub const BLOCK_ALIGN: usize = 16;
+
+struct Block {
+    size: usize,
+    next: *mut Block,
+}
+
+pub struct FreeList {
+    head: *mut Block,
+}
+
+pub fn align_up(value: usize, align: usize) -> usize {
+    (value + align - 1) & !(align - 1)
+}
+
+impl FreeList {
+    pub const fn new() -> Self {
+        Self {
+            head: null_mut(),
+        }
+    }
+
+    /// Отдаёт списку область `[start, start + size)`.
+    ///
+    /// # Safety
+    /// Область должна быть отображена, ни с чем не пересекаться и быть
+    /// выровнена на `BLOCK_ALIGN`, как и `size`.
+    pub unsafe fn add(&mut self, start: usize, size: usize) {
+        debug_assert!(start.is_multiple_of(BLOCK_ALIGN) && size.is_multiple_of(BLOCK_ALIGN));
+        if size == 0 {
+            return;
+        }
+
+        let mut prev: *mut Block = null_mut();
+        let mut next = self.head;
+        while !next.is_null() && (next as usize) < start {
+            prev = next;
+            next = (*next).next;
+        }
+
+        let block = start as *mut Block;
+        block.write(Block { size, next });
+
+        if !next.is_null() && start + size == next as usize {
+            (*block).size += (*next).size;
+            (*block).next = (*next).next;
+        }
+
+        if prev.is_null() {
+            self.head = block;
+        } else if prev as usize + (*prev).size == start {
+            (*prev).size += (*block).size;
+            (*prev).next = (*block).next;
+        } else {
+            (*prev).next = block;
+        }
+    }
+
+    /// Первый подходящий блок. `size` и `align` уже нормализованы вызывающим.
+    pub fn allocate(&mut self, size: usize, align: usize) -> *mut u8 {
+        let mut prev: *mut Block = null_mut();
+        let mut current = self.head;
+
+        unsafe {
+            while !current.is_null() {
+                let start = current as usize;
+                let end = start + (*current).size;
+                let aligned = align_up(start, align);
+
+                if aligned + size <= end {
+                    let next = (*current).next;
+                    // Хвост блока остаётся свободным, голову до выравнивания
+                    // тоже возвращаем в список
+                    if prev.is_null() {
+                        self.head = next;
+                    } else {
+                        (*prev).next = next;
+                    }
+                    self.add(start, aligned - start);
+                    self.add(aligned + size, end - aligned - size);
+                    return aligned as *mut u8;
+                }
+
+                prev = current;
+                current = (*current).next;
+            }
+        }
+        null_mut()
+    }
+
+    /// Сколько байт свободно в списке и размер самого большого блока.
+    pub fn totals(&self) -> (usize, usize) {
+        let (mut free, mut largest) = (0, 0);
+        let mut current = self.head;
+        while !current.is_null() {
+            unsafe {
+                free += (*current).size;
+                largest = largest.max((*current).size);
+                current = (*current).next;
+            }
+        }
+        (free, largest)
+    }
+}
@@ -0,0 +1,252 @@
+//! Куча ядра.
+//!
+//! Куча лежит с `HEAP_START`; при загрузке отображается `HEAP_INITIAL_SIZE`,
+//! дальше она растёт по мере нужды, пока не упрётся в `HEAP_MAX_SIZE` или
+//! в свободные кадры. Мелкие выделения обслуживают кэши `slab`, крупные —
+//! список свободных блоков. При нехватке памяти возвращается null, и тогда
+//! срабатывает `alloc_error_handler`. Счётчики и журнал выделений — в `stats`.
+
+use core::alloc::{GlobalAlloc, Layout};
+use core::ptr::null_mut;
+use x86_64::structures::paging::{Page, PageTableFlags, Size4KiB};
+use x86_64::VirtAddr;
+use crate::memory::{paging, FRAME_ALLOCATOR, FRAME_SIZE};
+use crate::spin::SpinMutex;
+
+mod free_list;
+mod slab;
+mod stats;
+
+use free_list::{align_up, FreeList, BLOCK_ALIGN};
+use slab::{class_for, Slabs};
+use stats::Tracker;
+
+pub use slab::SIZE_CLASSES;
+pub use stats::{ClassStats, HeapStats, Record, TRACK_CAPACITY};
+
+pub const HEAP_START: usize = 0x4444_4444_0000;
+pub const HEAP_INITIAL_SIZE: usize = 256 * 1024;
+pub const HEAP_MAX_SIZE: usize = 64 * 1024 * 1024;
+// Куча растёт не меньше чем на столько за раз
+const GROW_STEP: usize = 64 * 1024;
+
+struct Heap {
+    blocks: FreeList,
+    slabs: Slabs,
+    /// Конец отображённой части кучи.
+    end: usize,
+    stats: HeapStats,
+    tracker: Tracker,
+    /// Метка для журнала, см. `with_tag`.
+    tag: &'static str,
+    sequence: u64,
+}
+
+// Указатели внутри Heap ведут только в саму кучу
+unsafe impl Send for Heap {}
+
+pub struct LockedHeap(SpinMutex<Heap>);
+
+#[global_allocator]
+static ALLOCATOR: LockedHeap = LockedHeap(SpinMutex::new(Heap {
+    blocks: FreeList::new(),
+    slabs: Slabs::new(),
+    end: HEAP_START,
+    stats: HeapStats::new(),
+    tracker: Tracker::new(),
+    tag: "kernel",
+    sequence: 0,
+}));
+
+/// Размер и выравнивание блока в общем списке.
+fn block_layout(layout: &Layout) -> (usize, usize) {
+    (
+        align_up(layout.size().max(1), BLOCK_ALIGN),
+        layout.align().max(BLOCK_ALIGN),
+    )
+}
+
+impl Heap {
+    fn allocate_block(&mut self, size: usize, align: usize) -> *mut u8 {
+        let ptr = self.blocks.allocate(size, align);
+        if !ptr.is_null() {
+            return ptr;
+        }
+        // С запасом на выравнивание: новый кусок склеится с хвостом кучи,
+        // но полагаться на это нельзя
+        if !self.grow(size + align) {
+            return null_mut();
+        }
+        self.blocks.allocate(size, align)
+    }
+
+    /// Отображает ещё не меньше `min` байт в конце кучи.
+    fn grow(&mut self, min: usize) -> bool {
+        let size = align_up(min.max(GROW_STEP), FRAME_SIZE as usize);
+        let limit = HEAP_START + HEAP_MAX_SIZE;
+        let size = size.min(limit - self.end);
+        if size < min {
+            return false;
+        }
+
+        let mapped = map_pages(self.end, size);
+        if mapped > 0 {
+            unsafe { self.blocks.add(self.end, mapped) };
+            self.end += mapped;
+        }
+        mapped >= min
+    }
+}
+
+/// Отображает страницы с `start` на свежие кадры. Возвращает, сколько байт
+/// удалось отобразить: при нехватке кадров это может быть меньше `size`.
+fn map_pages(start: usize, size: usize) -> usize {
+    let flags = PageTableFlags::WRITABLE;
+    let mut mapped = 0;
+    while mapped < size {
+        let page = Page::<Size4KiB>::containing_address(VirtAddr::new((start + mapped) as u64));
+        // Кадр берём отдельно: paging::map сам захватывает FRAME_ALLOCATOR
+        let Some(frame) = FRAME_ALLOCATOR.lock().allocate() else {
+            break;
+        };
+        if paging::map(page, frame, flags).is_err() {
+            FRAME_ALLOCATOR.lock().deallocate(frame);
+            break;
+        }
+        mapped += FRAME_SIZE as usize;
+    }
+    mapped
+}
+
+impl Heap {
+    /// Выделяет блок и возвращает его вместе с настоящим размером.
+    fn allocate(&mut self, layout: &Layout) -> (*mut u8, usize) {
+        match class_for(layout.size(), layout.align()) {
+            Some(class) => {
+                let mut ptr = self.slabs.allocate(class, &mut self.blocks);
+                if ptr.is_null() && self.grow(slab::SLAB_SIZE * 2) {
+                    ptr = self.slabs.allocate(class, &mut self.blocks);
+                }
+                (ptr, SIZE_CLASSES[class])
+            }
+            None => {
+                let (size, align) = block_layout(layout);
+                (self.allocate_block(size, align), size)
+            }
+        }
+    }
+
+    fn class_stats(&mut self, layout: &Layout) -> &mut ClassStats {
+        match class_for(layout.size(), layout.align()) {
+            Some(class) => &mut self.stats.classes[class],
+            None => &mut self.stats.large,
+        }
+    }
+}
+
+unsafe impl GlobalAlloc for LockedHeap {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let mut heap = self.0.lock();
+        let (ptr, size) = heap.allocate(&layout);
+        if ptr.is_null() {
+            heap.stats.failures += 1;
+            return ptr;
+        }
+
+        heap.sequence += 1;
+        heap.stats.allocations += 1;
+        heap.stats.in_use += size;
+        heap.stats.peak = heap.stats.peak.max(heap.stats.in_use);
+        let class = heap.class_stats(&layout);
+        class.live += 1;
+        class.total += 1;
+
+        if heap.tracker.enabled {
+            let record = Record {
+                addr: ptr as usize,
+                size: layout.size(),
+                tag: heap.tag,
+                sequence: heap.sequence,
+            };
+            heap.tracker.insert(record);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        let mut heap = self.0.lock();
+        let size = match class_for(layout.size(), layout.align()) {
+            Some(class) => {
+                heap.slabs.deallocate(class, ptr);
+                SIZE_CLASSES[class]
+            }
+            None => {
+                let (size, _) = block_layout(&layout);
+                heap.blocks.add(ptr as usize, size);
+                size
+            }
+        };
+
+        heap.stats.frees += 1;
+        heap.stats.in_use -= size;
+        heap.class_stats(&layout).live -= 1;
+        if heap.tracker.enabled {
+            heap.tracker.remove(ptr as usize);
+        }
+    }
+}
+
+/// Отображает начальную часть кучи. Вызывается после `memory::init`.
+pub fn init() -> Result<(), &'static str> {
+    let mut heap = ALLOCATOR.0.lock();
+    if heap.end != HEAP_START {
+        return Err("heap is already initialized");
+    }
+    if !heap.grow(HEAP_INITIAL_SIZE) {
+        return Err("out of frames for the heap");
+    }
+    Ok(())
+}
+
+pub fn stats() -> HeapStats {
+    let heap = ALLOCATOR.0.lock();
+    let mut stats = heap.stats;
+    stats.mapped = heap.end - HEAP_START;
+    (stats.free_list, stats.largest_free) = heap.blocks.totals();
+
+    let carved: usize = (0..SIZE_CLASSES.len()).map(|class| heap.slabs.pages(class) * slab::SLAB_SIZE).sum();
+    let live: usize = SIZE_CLASSES.iter().zip(&stats.classes).map(|(size, class)| size * class.live).sum();
+    stats.slab_cached = carved - live;
+    stats
+}
+
+/// Выполняет `f`, помечая выделения в журнале как сделанные из `tag`.
+pub fn with_tag<R>(tag: &'static str, f: impl FnOnce() -> R) -> R {
+    let previous = core::mem::replace(&mut ALLOCATOR.0.lock().tag, tag);
+    let result = f();
+    ALLOCATOR.0.lock().tag = previous;
+    result
+}
+
+/// Включает или выключает журнал. При включении журнал очищается: выделения,
+/// сделанные до этого, в нём не появятся.
+pub fn set_tracking(enabled: bool) {
+    let mut heap = ALLOCATOR.0.lock();
+    if enabled && !heap.tracker.enabled {
+        heap.tracker.clear();
+    }
+    heap.tracker.enabled = enabled;
+}
+
+pub fn is_tracking() -> bool {
+    ALLOCATOR.0.lock().tracker.enabled
+}
+
+/// Передаёт `f` живые выделения из журнала и возвращает число не
+/// попавших в таблицу. Куча заблокирована на всё время обхода, так что
+/// `f` не должна выделять память.
+pub fn for_each_record(mut f: impl FnMut(&Record)) -> u64 {
+    let heap = ALLOCATOR.0.lock();
+    heap.tracker.records().for_each(&mut f);
+    heap.tracker.dropped
+}
@@ -0,0 +1,74 @@
+//! Кэши блоков фиксированного размера для мелких выделений.
+//!
+//! Каждый класс держит свой список свободных блоков. Когда список пуст,
+//! из общего списка берётся страница и режется на блоки класса. Блоки
+//! возвращаются в свой класс, а не в общий список.
+
+use core::ptr::null_mut;
+use super::free_list::FreeList;
+
+pub const SIZE_CLASSES: [usize; 8] = [16, 32, 64, 128, 256, 512, 1024, 2048];
+pub const SLAB_SIZE: usize = 4096;
+
+struct Slot {
+    next: *mut Slot,
+}
+
+pub struct Slabs {
+    heads: [*mut Slot; SIZE_CLASSES.len()],
+    /// Сколько страниц нарезано на блоки каждого класса.
+    pages: [usize; SIZE_CLASSES.len()],
+}
+
+/// Класс, в который помещается блок с таким размером и выравниванием.
+/// Блоки класса выровнены на его размер, потому что страница выровнена на 4 КиБ.
+pub fn class_for(size: usize, align: usize) -> Option<usize> {
+    let size = size.max(align);
+    SIZE_CLASSES.iter().position(|&class| size <= class)
+}
+
+impl Slabs {
+    pub const fn new() -> Self {
+        Self {
+            heads: [null_mut(); SIZE_CLASSES.len()],
+            pages: [0; SIZE_CLASSES.len()],
+        }
+    }
+
+    pub fn allocate(&mut self, class: usize, backing: &mut FreeList) -> *mut u8 {
+        if self.heads[class].is_null() && !self.refill(class, backing) {
+            return null_mut();
+        }
+        let slot = self.heads[class];
+        unsafe {
+            self.heads[class] = (*slot).next;
+        }
+        slot as *mut u8
+    }
+
+    pub fn pages(&self, class: usize) -> usize {
+        self.pages[class]
+    }
+
+    /// # Safety
+    /// `ptr` должен быть выдан этим же классом.
+    pub unsafe fn deallocate(&mut self, class: usize, ptr: *mut u8) {
+        let slot = ptr as *mut Slot;
+        slot.write(Slot { next: self.heads[class] });
+        self.heads[class] = slot;
+    }
+
+    fn refill(&mut self, class: usize, backing: &mut FreeList) -> bool {
+        let page = backing.allocate(SLAB_SIZE, SLAB_SIZE);
+        if page.is_null() {
+            return false;
+        }
+        self.pages[class] += 1;
+        let size = SIZE_CLASSES[class];
+        // Режем с конца, чтобы блоки выдавались по возрастанию адресов
+        for offset in (0..SLAB_SIZE).step_by(size).rev() {
+            unsafe { self.deallocate(class, page.add(offset)) };
+        }
+        true
+    }
+}
@@ -0,0 +1,112 @@
+//! Счётчики кучи и журнал живых выделений для поиска утечек.
+//!
+//! Журнал включается по требованию: каждое новое выделение записывается
+//! вместе с меткой места, откуда его сделали (шелл ставит имя команды),
+//! и вычёркивается при освобождении. Таблица фиксированного размера и лежит
+//! вне кучи, при переполнении лишние выделения только подсчитываются.
+
+use super::slab::SIZE_CLASSES;
+
+pub const TRACK_CAPACITY: usize = 256;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ClassStats {
+    /// Живые блоки.
+    pub live: usize,
+    /// Выделено за всё время.
+    pub total: u64,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct HeapStats {
+    pub mapped: usize,
+    pub in_use: usize,
+    pub peak: usize,
+    pub allocations: u64,
+    pub frees: u64,
+    pub failures: u64,
+    pub classes: [ClassStats; SIZE_CLASSES.len()],
+    /// Выделения крупнее самого большого класса.
+    pub large: ClassStats,
+    /// Нарезанные, но свободные блоки в кэшах классов.
+    pub slab_cached: usize,
+    pub free_list: usize,
+    pub largest_free: usize,
+}
+
+const NO_CLASS: ClassStats = ClassStats { live: 0, total: 0 };
+
+impl HeapStats {
+    pub const fn new() -> Self {
+        Self {
+            mapped: 0,
+            in_use: 0,
+            peak: 0,
+            allocations: 0,
+            frees: 0,
+            failures: 0,
+            classes: [NO_CLASS; SIZE_CLASSES.len()],
+            large: NO_CLASS,
+            slab_cached: 0,
+            free_list: 0,
+            largest_free: 0,
+        }
+    }
+
+    /// Доля свободного списка, которую нельзя выдать одним блоком, в процентах.
+    pub fn fragmentation(&self) -> usize {
+        if self.free_list == 0 {
+            return 0;
+        }
+        100 - self.largest_free * 100 / self.free_list
+    }
+}
+
+/// Живое выделение, записанное журналом.
+#[derive(Debug, Clone, Copy)]
+pub struct Record {
+    pub addr: usize,
+    pub size: usize,
+    pub tag: &'static str,
+    /// Номер выделения, по нему видно, насколько оно старое.
+    pub sequence: u64,
+}
+
+pub struct Tracker {
+    pub enabled: bool,
+    records: [Option<Record>; TRACK_CAPACITY],
+    /// Выделения, которые не влезли в таблицу.
+    pub dropped: u64,
+}
+
+impl Tracker {
+    pub const fn new() -> Self {
+        Self {
+            enabled: false,
+            records: [None; TRACK_CAPACITY],
+            dropped: 0,
+        }
+    }
+
+    pub fn insert(&mut self, record: Record) {
+        match self.records.iter_mut().find(|slot| slot.is_none()) {
+            Some(slot) => *slot = Some(record),
+            None => self.dropped += 1,
+        }
+    }
+
+    pub fn remove(&mut self, addr: usize) {
+        if let Some(slot) = self.records.iter_mut().find(|slot| matches!(slot, Some(r) if r.addr == addr)) {
+            *slot = None;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.records = [None; TRACK_CAPACITY];
+        self.dropped = 0;
+    }
+
+    pub fn records(&self) -> impl Iterator<Item = &Record> {
+        self.records.iter().flatten()
+    }
+}
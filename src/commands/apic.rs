@@ -0,0 +1,134 @@
+use crate::acpi::madt::{Polarity, Trigger};
+use crate::drivers::apic::{self, io, LEGACY_IRQS};
+use crate::vga_buffer::Color;
+use crate::print;
+use super::pager::Pager;
+use super::registry::{flag, sub, CommandSpec, Usage};
+use super::{Args, CommandError};
+
+pub static COMMAND: CommandSpec = CommandSpec {
+    name: "apic",
+    aliases: &[],
+    summary: "Local APIC and I/O APIC information",
+    usage: Usage {
+        subcommands: &[
+            sub("status", &[], "Show LAPIC, its timer and the I/O APIC redirection table")
+                .with_options(&[flag("all", 'a', "Also list unused redirection entries")]),
+        ],
+        args: &[],
+    },
+    run: run_apic_command,
+};
+
+fn run_apic_command(args: &Args) -> Result<(), CommandError> {
+    match args.first() {
+        None => Err(CommandError::MissingSubcommand),
+        Some("status") => show_status(&args.rest()),
+        Some(_) => Err(args.unknown_subcommand()),
+    }
+}
+
+fn show_status(args: &Args) -> Result<(), CommandError> {
+    args.expect_at_most(0)?;
+    let (Some(local), Some(madt)) = (apic::local(), apic::madt()) else {
+        return Err(CommandError::Failed("APIC is not active, IRQs go through the 8259 PIC"));
+    };
+    let mut pager = Pager::new();
+
+    print!(("\nLocal APIC:"), fg: Color::LightBlue);
+    print!(("\n  ID: "), fg: Color::LightBlue);
+    print!(("{}", local.id()), fg: Color::White);
+    print!(("  Version: "), fg: Color::LightBlue);
+    print!(("{:#04x}", local.version()), fg: Color::White);
+    print!(("  LVT entries: "), fg: Color::LightBlue);
+    print!(("{}", local.max_lvt()), fg: Color::White);
+    print!(("\n  Address: "), fg: Color::LightBlue);
+    print!(("{:#x}", apic::local_phys()), fg: Color::White);
+    print!(("  Spurious: "), fg: Color::LightBlue);
+    print!(("{:#010x}, {} received", local.spurious(), apic::spurious_count()), fg: Color::White);
+
+    let timer = local.timer();
+    print!(("\n  Timer: "), fg: Color::LightBlue);
+    print!(("vector {:#04x}, {}, divide {}, ", timer.vector, timer.mode.name(), timer.divide), fg: Color::White);
+    print!(("count {}/{}", timer.current, timer.initial), fg: Color::White);
+    if timer.masked {
+        print!((" (masked)"), fg: Color::DarkGray);
+    }
+
+    print!(("\n  CPUs: "), fg: Color::LightBlue);
+    for (i, cpu) in madt.local_apics.iter().flatten().enumerate() {
+        let state = if cpu.enabled { "" } else { ", disabled" };
+        print!(("{}{} (APIC {}{})", if i > 0 { ", " } else { "" }, cpu.processor_id, cpu.apic_id, state), fg: Color::White);
+    }
+
+    for (index, io_apic) in madt.io_apics.iter().enumerate().filter_map(|(i, io)| io.map(|io| (i, io))) {
+        let Some(info) = apic::io_apic_info(index) else { continue };
+        if !pager.next_line() {
+            return Ok(());
+        }
+        print!(("\nI/O APIC {}: ", io_apic.id), fg: Color::LightBlue);
+        print!(("version {:#04x}, {} inputs from GSI {}, at {:#x}", info.version, info.entries, info.gsi_base, io_apic.address), fg: Color::White);
+        if !pager.next_line() {
+            return Ok(());
+        }
+        print!(("\n  Pin  Vector  Dest  Delivery  Trigger  Polarity  State   ISA"), fg: Color::LightBlue);
+
+        for pin in 0..info.entries {
+            let Some(entry) = apic::redirection(index, pin) else { break };
+            let irq = isa_irq(index, pin);
+            let masked = entry & io::ENTRY_MASKED != 0;
+            if !args.flag("all") && masked && irq.is_none() && io::entry_vector(entry) == 0 {
+                continue;
+            }
+            if !pager.next_line() {
+                return Ok(());
+            }
+            print_entry(pin, entry, irq);
+        }
+    }
+
+    if !pager.next_line() {
+        return Ok(());
+    }
+    print!(("\nISA overrides: "), fg: Color::LightBlue);
+    let mut any = false;
+    for source in madt.overrides.iter().flatten() {
+        let trigger = if source.trigger == Trigger::Level { ", level" } else { "" };
+        let polarity = if source.polarity == Polarity::Low { ", low" } else { "" };
+        print!(("{}IRQ {} -> GSI {}{}{}", if any { "; " } else { "" }, source.irq, source.gsi, trigger, polarity), fg: Color::White);
+        any = true;
+    }
+    if !any {
+        print!(("none"), fg: Color::DarkGray);
+    }
+    if !madt.has_8259 {
+        print!(("\n  No 8259 PIC according to MADT"), fg: Color::DarkGray);
+    }
+    Ok(())
+}
+
+/// Линия ISA, которую ядро направило на этот вход.
+fn isa_irq(index: usize, pin: u32) -> Option<u8> {
+    (0..LEGACY_IRQS).find(|&irq| {
+        apic::legacy_route(irq).is_some_and(|route| route.io_apic == index && route.pin == pin)
+    })
+}
+
+fn print_entry(pin: u32, entry: u64, irq: Option<u8>) {
+    let masked = entry & io::ENTRY_MASKED != 0;
+    let text = if masked { Color::DarkGray } else { Color::White };
+    print!(("\n  {:>3}  {:#06x}  {:>4}  {:<8}  ", pin, io::entry_vector(entry), io::entry_destination(entry), io::entry_delivery(entry)), fg: text);
+    print!(("{:<7}  ", if entry & io::ENTRY_TRIGGER_LEVEL != 0 { "level" } else { "edge" }), fg: text);
+    print!(("{:<8}  ", if entry & io::ENTRY_POLARITY_LOW != 0 { "low" } else { "high" }), fg: text);
+    if masked {
+        print!(("masked  "), fg: Color::DarkGray);
+    } else if entry & io::ENTRY_REMOTE_IRR != 0 {
+        print!(("pending "), fg: Color::Yellow);
+    } else {
+        print!(("active  "), fg: Color::LightGreen);
+    }
+    match irq {
+        Some(irq) => print!(("IRQ {}", irq), fg: Color::LightCyan),
+        None => print!(("-"), fg: Color::DarkGray),
+    }
+}
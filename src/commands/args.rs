@@ -0,0 +1,243 @@
+//! Разбор аргументов команд: позиционные слова, опции `--name`/`-n` и числа.
+//!
+//! Числа везде пишутся одинаково: `0x1F`, `0b101`, `0o17` или десятичные,
+//! с необязательным множителем `K`, `M`, `G` (степени 1024).
+
+use core::num::IntErrorKind;
+use super::command::CommandError;
+use super::registry::{ArgSpec, OptionSpec};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NumberError {
+    /// Только префикс или множитель, без цифр.
+    Empty,
+    InvalidDigit,
+    Overflow,
+}
+
+impl NumberError {
+    pub fn reason(self) -> &'static str {
+        match self {
+            NumberError::Empty => "number has no digits",
+            NumberError::InvalidDigit => "expected a number like 0x1F, 0b101, 0o17, 42 or 4K",
+            NumberError::Overflow => "number is too large",
+        }
+    }
+}
+
+pub fn parse_number(text: &str) -> Result<u64, NumberError> {
+    let (digits, multiplier) = match text.as_bytes().last() {
+        Some(b'K' | b'k') => (&text[..text.len() - 1], 1u64 << 10),
+        Some(b'M' | b'm') => (&text[..text.len() - 1], 1 << 20),
+        Some(b'G' | b'g') => (&text[..text.len() - 1], 1 << 30),
+        _ => (text, 1),
+    };
+
+    let (digits, radix) = split_radix(digits);
+    if digits.is_empty() {
+        return Err(NumberError::Empty);
+    }
+
+    let value = u64::from_str_radix(digits, radix).map_err(|error| match error.kind() {
+        IntErrorKind::PosOverflow => NumberError::Overflow,
+        _ => NumberError::InvalidDigit,
+    })?;
+    value.checked_mul(multiplier).ok_or(NumberError::Overflow)
+}
+
+fn split_radix(text: &str) -> (&str, u32) {
+    let prefixes = [("0x", 16), ("0X", 16), ("0b", 2), ("0B", 2), ("0o", 8), ("0O", 8)];
+    for (prefix, radix) in prefixes {
+        if let Some(digits) = text.strip_prefix(prefix) {
+            return (digits, radix);
+        }
+    }
+    (text, 10)
+}
+
+/// `-5` считается числом, а не опцией.
+pub fn is_option(word: &str) -> bool {
+    let mut chars = word.chars();
+    chars.next() == Some('-') && chars.next().is_some_and(|c| !c.is_ascii_digit())
+}
+
+pub fn lookup(options: &'static [OptionSpec], word: &str) -> Option<&'static OptionSpec> {
+    if let Some(long) = word.strip_prefix("--") {
+        return options.iter().find(|spec| spec.long == long);
+    }
+
+    let mut chars = word.strip_prefix('-')?.chars();
+    match (chars.next(), chars.next()) {
+        (Some(short), None) => options.iter().find(|spec| spec.short == short),
+        _ => None,
+    }
+}
+
+/// Значение опции, если `word` — опция со значением.
+pub fn option_value(options: &'static [OptionSpec], word: &str) -> Option<&'static ArgSpec> {
+    lookup(options, word).and_then(|spec| spec.value.as_ref())
+}
+
+/// Аргументы после имени команды. Номера в ошибках — позиции слов во всей
+/// строке аргументов, даже если команда уже отрезала подкоманду: по ним
+/// шелл подсвечивает слово, в котором ошибка.
+#[derive(Clone, Copy)]
+pub struct Args<'a> {
+    words: &'a [&'a str],
+    start: usize,
+    options: &'static [OptionSpec],
+}
+
+impl<'a> Args<'a> {
+    /// `options` — опции, которые понимает вызванная подкоманда.
+    pub fn new(words: &'a [&'a str], options: &'static [OptionSpec]) -> Self {
+        Self { words, start: 0, options }
+    }
+
+    /// Слово строки как есть, по номеру из ошибки.
+    pub fn word(&self, index: usize) -> Option<&'a str> {
+        self.words.get(index).copied()
+    }
+
+    pub fn words(&self) -> &'a [&'a str] {
+        self.words
+    }
+
+    /// Номера позиционных слов, начиная с `start`: опции и их значения пропускаются.
+    fn positions(&self) -> impl Iterator<Item = usize> + '_ {
+        let mut skip_value = false;
+        (self.start..self.words.len()).filter(move |&index| {
+            if skip_value {
+                skip_value = false;
+                return false;
+            }
+            let word = self.words[index];
+            if !is_option(word) {
+                return true;
+            }
+            skip_value = option_value(self.options, word).is_some();
+            false
+        })
+    }
+
+    fn position(&self, index: usize) -> Option<usize> {
+        self.positions().nth(index)
+    }
+
+    pub fn len(&self) -> usize {
+        self.positions().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.position(0).is_none()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&'a str> {
+        self.position(index).map(|position| self.words[position])
+    }
+
+    pub fn first(&self) -> Option<&'a str> {
+        self.get(0)
+    }
+
+    /// Всё после первого слова, обычно аргументы подкоманды.
+    pub fn rest(&self) -> Args<'a> {
+        Args {
+            start: self.position(0).map_or(self.words.len(), |position| position + 1),
+            ..*self
+        }
+    }
+
+    pub fn required(&self, index: usize, name: &'static str) -> Result<&'a str, CommandError> {
+        self.get(index).ok_or(CommandError::MissingArgument(name))
+    }
+
+    /// Ошибка, если аргументов больше `count`.
+    pub fn expect_at_most(&self, count: usize) -> Result<(), CommandError> {
+        match self.position(count) {
+            Some(index) => Err(CommandError::UnexpectedArgument { index }),
+            None => Ok(()),
+        }
+    }
+
+    pub fn number<T: TryFrom<u64>>(&self, index: usize, name: &'static str) -> Result<T, CommandError> {
+        let word = self.required(index, name)?;
+        to_number(word).map_err(|error| self.invalid(index, error.reason()))
+    }
+
+    pub fn optional_number<T: TryFrom<u64>>(&self, index: usize) -> Result<Option<T>, CommandError> {
+        match self.get(index) {
+            Some(word) => to_number(word).map(Some).map_err(|error| self.invalid(index, error.reason())),
+            None => Ok(None),
+        }
+    }
+
+    pub fn invalid(&self, index: usize, reason: &'static str) -> CommandError {
+        let index = self.position(index).unwrap_or(self.words.len());
+        CommandError::InvalidArgument { index, reason }
+    }
+
+    pub fn unknown_subcommand(&self) -> CommandError {
+        CommandError::UnknownSubcommand { index: self.position(0).unwrap_or(self.start) }
+    }
+
+    /// Проверяет опции всей строки до запуска команды.
+    pub fn check_options(&self) -> Result<(), CommandError> {
+        let mut skip_value = false;
+        for (index, &word) in self.words.iter().enumerate() {
+            if skip_value {
+                skip_value = false;
+                continue;
+            }
+            if !is_option(word) {
+                continue;
+            }
+            let spec = lookup(self.options, word).ok_or(CommandError::UnknownOption { index })?;
+            if spec.value.is_some() {
+                if index + 1 == self.words.len() {
+                    return Err(CommandError::MissingOptionValue { index });
+                }
+                skip_value = true;
+            }
+        }
+        Ok(())
+    }
+
+    /// Номер слова с опцией `long` (или её короткой формой).
+    fn find_option(&self, long: &str) -> Option<usize> {
+        self.words
+            .iter()
+            .position(|word| is_option(word) && lookup(self.options, word).is_some_and(|spec| spec.long == long))
+    }
+
+    pub fn flag(&self, long: &str) -> bool {
+        self.find_option(long).is_some()
+    }
+
+    pub fn option(&self, long: &str) -> Option<&'a str> {
+        self.find_option(long).and_then(|index| self.word(index + 1))
+    }
+
+    pub fn option_number<T: TryFrom<u64>>(&self, long: &str) -> Result<Option<T>, CommandError> {
+        match self.find_option(long) {
+            Some(index) => {
+                let word = self.word(index + 1).ok_or(CommandError::MissingOptionValue { index })?;
+                to_number(word)
+                    .map(Some)
+                    .map_err(|error| CommandError::InvalidArgument { index: index + 1, reason: error.reason() })
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Ошибка в значении опции `long`, которое само по себе разобралось.
+    pub fn invalid_option(&self, long: &str, reason: &'static str) -> CommandError {
+        let index = self.find_option(long).map_or(self.words.len(), |index| index + 1);
+        CommandError::InvalidArgument { index, reason }
+    }
+}
+
+fn to_number<T: TryFrom<u64>>(word: &str) -> Result<T, NumberError> {
+    let value = parse_number(word)?;
+    T::try_from(value).map_err(|_| NumberError::Overflow)
+}
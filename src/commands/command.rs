@@ -0,0 +1,32 @@
+//! Интерфейс команды шелла и её ошибки.
+
+use super::args::Args;
+use super::registry::Usage;
+
+pub trait Command: Sync {
+    fn name(&self) -> &'static str;
+
+    fn aliases(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    fn summary(&self) -> &'static str;
+
+    /// Подкоманды и аргументы: по ним строятся справка и дополнение.
+    fn usage(&self) -> &Usage;
+
+    fn run(&self, args: &Args) -> Result<(), CommandError>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommandError {
+    /// Команда без подкоманды: вместо ошибки показывается справка.
+    MissingSubcommand,
+    UnknownSubcommand { index: usize },
+    MissingArgument(&'static str),
+    InvalidArgument { index: usize, reason: &'static str },
+    UnexpectedArgument { index: usize },
+    UnknownOption { index: usize },
+    MissingOptionValue { index: usize },
+    Failed(&'static str),
+}
@@ -1,8 +1,11 @@
 #![allow(dead_code)]
 
+use crate::drivers::tsc;
 use crate::vga_buffer::Color;
 use crate::print;
 use core::arch::asm;
+use super::registry::{sub, CommandSpec, Usage};
+use super::{Args, CommandError};
 
 const MAX_STRING_LEN: usize = 48;
 
@@ -177,6 +180,19 @@ pub fn show_cpu_info() {
     print!(("\n  Threads: "), fg: Color::LightBlue);
     print!(("{}", cpu_info.threads), fg: Color::White);
 
+    print!(("\n  Frequency: "), fg: Color::LightBlue);
+    match tsc::frequency() {
+        0 => print!(("unknown"), fg: Color::DarkGray),
+        hz => {
+            print!(("{}.{:02} MHz", hz / 1_000_000, hz / 10_000 % 100), fg: Color::White);
+            if tsc::is_invariant() {
+                print!((" (invariant TSC)"), fg: Color::DarkGray);
+            } else {
+                print!((" (TSC may drift)"), fg: Color::Yellow);
+            }
+        }
+    }
+
     print!(("\n"));
 
     print!(("\n  Family: "), fg: Color::LightBlue);
@@ -220,25 +236,34 @@ pub fn show_cpu_info() {
     
 }
 
-pub fn handle_cpu_command(args: &[&str]) {
-    if args.is_empty() {
-        show_cpu_info();
-        print!(("\n"));
-        print!(("\nFor help use cpu --help"), fg: Color::LightGray);
-        return;
-    }
+pub static COMMAND: CommandSpec = CommandSpec {
+    name: "cpu",
+    aliases: &[],
+    summary: "CPU information and control",
+    usage: Usage {
+        subcommands: &[
+            sub("info", &[], "Show CPU information"),
+            sub("set", &[], "Set CPU register (not implemented)"),
+            sub("halt", &[], "Halt the CPU"),
+        ],
+        args: &[],
+    },
+    run: run_cpu_command,
+};
 
-    match args[0] {
-        "info" => show_cpu_info(),
-        "set" => handle_set_command(&args[1..]),
-        "halt" => halt_cpu(),
-        "--help" | "-h" | "help" => print_help(),
-        _ => print!(("\nUnknown CPU command. Try 'cpu --help' for usage."), fg: Color::Red),
+fn run_cpu_command(args: &Args) -> Result<(), CommandError> {
+    match args.first() {
+        None => {
+            show_cpu_info();
+            print!(("\n"));
+            print!(("\nFor help use cpu --help"), fg: Color::LightGray);
+        }
+        Some("info") => show_cpu_info(),
+        Some("set") => return Err(CommandError::Failed("CPU register setting is not implemented yet")),
+        Some("halt") => halt_cpu(),
+        Some(_) => return Err(args.unknown_subcommand()),
     }
-}
-
-fn handle_set_command(_args: &[&str]) {
-    print!(("\nError: CPU register setting is not implemented yet"), fg: Color::Red);
+    Ok(())
 }
 
 fn halt_cpu() {
@@ -249,11 +274,3 @@ fn halt_cpu() {
         }
     }
 }
-
-fn print_help() {
-    print!(("\nCPU Commands:"), fg: Color::LightBlue);
-    print!(("\n  cpu info       - Show CPU information"), fg: Color::White);
-    print!(("\n  cpu set        - Set CPU register (not implemented)"), fg: Color::White);
-    print!(("\n  cpu halt      - Halt the CPU"), fg: Color::White);
-    print!(("\n  cpu --help    - Show this help"), fg: Color::White);
-}
@@ -0,0 +1,88 @@
+use crate::drivers::rtc::{self, DateTime};
+use crate::shell::clock;
+use crate::vga_buffer::Color;
+use crate::print;
+use super::registry::{arg, sub, ArgKind, CommandSpec, Usage};
+use super::{Args, CommandError};
+
+pub static COMMAND: CommandSpec = CommandSpec {
+    name: "date",
+    aliases: &["time"],
+    summary: "Show or set the real-time clock",
+    usage: Usage {
+        subcommands: &[
+            sub("set", &[arg("YYYY-MM-DD", ArgKind::Text), arg("HH:MM:SS", ArgKind::Text)], "Set the date and time"),
+            sub("clock", &[arg("state", ArgKind::Choice(&["on", "off"]))], "Show a clock in the top-right corner of the screen"),
+        ],
+        args: &[],
+    },
+    run: run_date_command,
+};
+
+fn run_date_command(args: &Args) -> Result<(), CommandError> {
+    match args.first() {
+        None => {
+            show_date();
+            Ok(())
+        }
+        Some("set") => handle_set(&args.rest()),
+        Some("clock") => handle_clock(&args.rest()),
+        Some(_) => Err(args.unknown_subcommand()),
+    }
+}
+
+fn show_date() {
+    let now = rtc::read();
+    print!(("\n{} {}", now.weekday(), now), fg: Color::LightGreen);
+}
+
+fn handle_set(args: &Args) -> Result<(), CommandError> {
+    args.expect_at_most(2)?;
+    let [year, month, day] = split_fields(args.required(0, "date")?, '-').ok_or(args.invalid(0, "expected YYYY-MM-DD"))?;
+    let [hour, minute, second] = split_fields(args.required(1, "time")?, ':').ok_or(args.invalid(1, "expected HH:MM:SS"))?;
+
+    // Поля больше 255 всё равно вне диапазона, обрезаются до 255
+    let field = |value: u32| value.min(u8::MAX as u32) as u8;
+    let time = DateTime {
+        year: year.min(u16::MAX as u32) as u16,
+        month: field(month),
+        day: field(day),
+        hour: field(hour),
+        minute: field(minute),
+        second: field(second),
+    };
+    let midnight = DateTime { hour: 0, minute: 0, second: 0, ..time };
+    if !midnight.is_valid() {
+        return Err(args.invalid(0, "date must be 1900-01-01 to 2099-12-31"));
+    }
+    if !time.is_valid() {
+        return Err(args.invalid(1, "time is out of range"));
+    }
+
+    rtc::set(&time).map_err(CommandError::Failed)?;
+    print!(("\nClock set to {}", time), fg: Color::LightGreen);
+    Ok(())
+}
+
+/// Три десятичных числа через `separator`.
+fn split_fields(text: &str, separator: char) -> Option<[u32; 3]> {
+    let mut fields = [0; 3];
+    let mut parts = text.split(separator);
+    for field in fields.iter_mut() {
+        *field = parts.next()?.parse().ok()?;
+    }
+    if parts.next().is_some() {
+        return None;
+    }
+    Some(fields)
+}
+
+fn handle_clock(args: &Args) -> Result<(), CommandError> {
+    args.expect_at_most(1)?;
+    match args.required(0, "state")? {
+        "on" => clock::set_enabled(true),
+        "off" => clock::set_enabled(false),
+        _ => return Err(args.invalid(0, "expected on or off")),
+    }
+    Ok(())
+}
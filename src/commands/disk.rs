@@ -1,53 +1,53 @@
 use crate::vga_buffer::Color;
 use crate::print;
 use crate::drivers::ata::{AtaController, AtaDevice};
+use super::registry::{arg, optional, sub, ArgKind, CommandSpec, Usage};
+use super::{Args, CommandError};
 
-pub fn handle_disk_command(args: &[&str]) {
-    if args.is_empty() || args[0] == "help" {
-        disk_info();
-        print!(("\n"));
-        print!(("\nFor help use disk --help"), fg: Color::LightGray);
-        return;
-    }
+const MAX_SECTORS: u32 = 8;
 
-    match args[0] {
-        "info" => disk_info(),
-        "read" => {
-            if args.len() < 2 {
-                print!(("\nError: missing sector number"), fg: Color::Red);
-                print_help();
-                return;
-            }
-            
-            let sector = match parse_hex_u32(args[1]) {
-                Some(addr) => addr,
-                None => {
-                    print!(("\nError: invalid sector number (use hex with 0x prefix)"), fg: Color::Red);
-                    return;
-                }
-            };
-            
-            let count = if args.len() > 2 {
-                match parse_hex_u32(args[2]) {
-                    Some(n) if n > 0 && n <= 256 => n as u8,
-                    _ => {
-                        print!(("\nError: invalid sector count (1-256 in hex with 0x prefix)"), fg: Color::Red);
-                        return;
-                    }
-                }
-            } else { 1 };
-            
-            read_sectors(sector, count);
-        },
-        _ => print!(("\nUnknown disk command. Type 'disk help' for usage."), fg: Color::Red),
+pub static COMMAND: CommandSpec = CommandSpec {
+    name: "disk",
+    aliases: &[],
+    summary: "Disk operations and information",
+    usage: Usage {
+        subcommands: &[
+            sub("info", &[], "Show disk information"),
+            sub("read", &[arg("sector", ArgKind::Number), optional("count", ArgKind::Number)], "Read sectors"),
+        ],
+        args: &[],
+    },
+    run: run_disk_command,
+};
+
+fn run_disk_command(args: &Args) -> Result<(), CommandError> {
+    match args.first() {
+        None => {
+            disk_info();
+            print!(("\n"));
+            print!(("\nFor help use disk --help"), fg: Color::LightGray);
+            Ok(())
+        }
+        Some("info") => {
+            disk_info();
+            Ok(())
+        }
+        Some("read") => handle_read_command(&args.rest()),
+        Some(_) => Err(args.unknown_subcommand()),
     }
 }
 
-fn print_help() {
-    print!(("\nDisk commands:"), fg: Color::LightBlue);
-    print!(("\n  disk info                - Show disk information"), fg: Color::White);
-    print!(("\n  disk read <sector> [count] - Read sectors from disk (hex values with 0x)"), fg: Color::White);
-    print!(("\n  disk --help               - Show this help"), fg: Color::White);
+fn handle_read_command(args: &Args) -> Result<(), CommandError> {
+    args.expect_at_most(2)?;
+    let sector: u32 = args.number(0, "sector")?;
+
+    let count = match args.optional_number::<u32>(1)? {
+        Some(n) if n > 0 && n <= MAX_SECTORS => n as u8,
+        Some(_) => return Err(args.invalid(1, "count must be 1-8")),
+        None => 1,
+    };
+
+    read_sectors(sector, count)
 }
 
 fn disk_info() {
@@ -97,11 +97,7 @@ fn disk_info() {
     }
 }
 
-fn read_sectors(sector: u32, count: u8) {
-    if count == 0 || count > 8 {
-        print!(("\nError: Count must be between 1 and 8"), fg: Color::Red);
-        return;
-    }
+fn read_sectors(sector: u32, count: u8) -> Result<(), CommandError> {
     
     print!(("\nReading {} sector(s) from LBA 0x{:X}", count, sector));
     
@@ -116,7 +112,7 @@ fn read_sectors(sector: u32, count: u8) {
     
     for (name, device) in &devices {
         let controller = AtaController::new(*device);
-        let mut buffer = [0u8; 512 * 8];
+        let mut buffer = [0u8; 512 * MAX_SECTORS as usize];
         
         print!(("\n  Trying {}... ", name));
         
@@ -161,11 +157,7 @@ fn read_sectors(sector: u32, count: u8) {
     }
     
     if !found {
-        print!(("\nError: Could not read from any ATA device"), fg: Color::Red);
+        return Err(CommandError::Failed("could not read from any ATA device"));
     }
-}
-
-fn parse_hex_u32(s: &str) -> Option<u32> {
-    let s = s.trim_start_matches("0x");
-    u32::from_str_radix(s, 16).ok()
+    Ok(())
 }
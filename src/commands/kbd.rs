@@ -0,0 +1,61 @@
+use crate::drivers::keyboard::layouts;
+use crate::vga_buffer::Color;
+use crate::print;
+use super::registry::{optional, sub, ArgKind, CommandSpec, Usage};
+use super::{Args, CommandError};
+
+pub static COMMAND: CommandSpec = CommandSpec {
+    name: "kbd",
+    aliases: &[],
+    summary: "Keyboard layout settings",
+    usage: Usage {
+        subcommands: &[
+            sub("layout", &[optional("name", ArgKind::Dynamic(layout_names))], "Show or switch the keyboard layout"),
+        ],
+        args: &[],
+    },
+    run: run_kbd_command,
+};
+
+fn layout_names(out: &mut dyn FnMut(&'static str)) {
+    for layout in layouts::LAYOUTS.iter() {
+        out(layout.name);
+    }
+}
+
+fn run_kbd_command(args: &Args) -> Result<(), CommandError> {
+    match args.first() {
+        None => Err(CommandError::MissingSubcommand),
+        Some("layout") => handle_layout_command(&args.rest()),
+        Some(_) => Err(args.unknown_subcommand()),
+    }
+}
+
+fn handle_layout_command(args: &Args) -> Result<(), CommandError> {
+    args.expect_at_most(1)?;
+    let Some(name) = args.first() else {
+        let active = layouts::active();
+        print!(("\nCurrent layout: "), fg: Color::LightBlue);
+        print!(("{} ({})", active.name, active.description), fg: Color::White);
+        list_layouts();
+        return Ok(());
+    };
+
+    match layouts::select(name) {
+        Some(layout) => {
+            print!(("\nKeyboard layout set to {} ({})", layout.name, layout.description), fg: Color::LightGreen);
+            Ok(())
+        }
+        None => {
+            list_layouts();
+            Err(args.invalid(0, "unknown layout"))
+        }
+    }
+}
+
+fn list_layouts() {
+    print!(("\nAvailable layouts (Alt+Shift to switch):"), fg: Color::LightBlue);
+    for layout in layouts::LAYOUTS.iter() {
+        print!(("\n  {:<4} - {}", layout.name, layout.description), fg: Color::White);
+    }
+}
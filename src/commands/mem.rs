@@ -1,162 +1,616 @@
+use x86_64::registers::control::Cr3;
+use x86_64::structures::paging::PageTableFlags;
 use x86_64::VirtAddr;
+use crate::allocator::{self, SIZE_CLASSES};
+use crate::memory::{self, paging, FRAME_SIZE};
+use crate::multiboot;
 use crate::vga_buffer::Color;
 use crate::print;
+use super::pager::Pager;
+use super::registry::{arg, flag, option, optional, sub, ArgKind, CommandSpec, OptionSpec, Usage};
+use super::{Args, CommandError};
 
-pub fn handle_mem_command(args: &[&str]) {
-    if args.is_empty() || args[0] == "--help" {
-        print_help();
-        return;
-    }
+pub static COMMAND: CommandSpec = CommandSpec {
+    name: "mem",
+    aliases: &[],
+    summary: "Memory operations",
+    usage: Usage {
+        subcommands: &[
+            sub("read", &[arg("address", ArgKind::Address), optional("length", ArgKind::Number)], "Dump memory (default: 16 bytes)")
+                .with_options(&ELEMENT_OPTIONS),
+            sub("write", &[arg("address", ArgKind::Address), arg("value", ArgKind::Number)], "Write a value to memory")
+                .with_options(&ELEMENT_OPTIONS),
+            sub("fill", &[arg("address", ArgKind::Address), arg("length", ArgKind::Number), arg("value", ArgKind::Number)], "Fill memory with a value")
+                .with_options(&ELEMENT_OPTIONS),
+            sub("copy", &[arg("destination", ArgKind::Address), arg("source", ArgKind::Address), arg("length", ArgKind::Number)], "Copy memory, ranges may overlap"),
+            sub("cmp", &[arg("first", ArgKind::Address), arg("second", ArgKind::Address), arg("length", ArgKind::Number)], "Compare two memory ranges"),
+            sub("find", &[arg("address", ArgKind::Address), arg("length", ArgKind::Number), arg("pattern", ArgKind::Text)], "Search for hex bytes or text")
+                .with_options(&[flag("text", 's', "Treat the pattern as text")]),
+            sub("translate", &[arg("address", ArgKind::Address)], "Show where a virtual address is mapped"),
+            sub("pt", &[optional("address", ArgKind::Address)], "Show the page table walk for an address, or all mapped ranges"),
+            sub("frames", &[], "Show physical frame usage")
+                .with_options(&[flag("map", 'm', "Also print the bootloader memory map")]),
+            sub("heap", &[], "Show kernel heap usage")
+                .with_options(&[
+                    flag("live", 'l', "List live allocations recorded by tracking"),
+                    option("track", 't', arg("state", ArgKind::Choice(&["on", "off"])), "Record the command behind each new allocation"),
+                ]),
+        ],
+        args: &[],
+    },
+    run: run_mem_command,
+};
+
+static ELEMENT_OPTIONS: [OptionSpec; 2] = [
+    option("type", 't', arg("type", ArgKind::Choice(&["u8", "u16", "u32", "u64"])), "Element type (default: u8)"),
+    flag("big-endian", 'b', "Show and write elements as big-endian"),
+];
 
-    match args[0] {
-        "read" => handle_mem_read(&args[1..]),
-        "write" => handle_mem_write(&args[1..]),
-        _ => print!(("\nUnknown memory command. Type 'mem --help' for usage."), fg: Color::Red),
+// Дальше вывод всё равно листается постранично, предел — от опечаток
+const MAX_DUMP: usize = 64 * 1024;
+const MAX_PATTERN: usize = 64;
+
+fn run_mem_command(args: &Args) -> Result<(), CommandError> {
+    match args.first() {
+        None => Err(CommandError::MissingSubcommand),
+        Some("read") => handle_mem_read(&args.rest()),
+        Some("write") => handle_mem_write(&args.rest()),
+        Some("fill") => handle_mem_fill(&args.rest()),
+        Some("copy") => handle_mem_copy(&args.rest()),
+        Some("cmp") => handle_mem_cmp(&args.rest()),
+        Some("find") => handle_mem_find(&args.rest()),
+        Some("translate") => handle_translate_command(&args.rest()),
+        Some("pt") => handle_pt_command(&args.rest()),
+        Some("frames") => handle_frames_command(&args.rest()),
+        Some("heap") => handle_heap_command(&args.rest()),
+        Some(_) => Err(args.unknown_subcommand()),
     }
 }
 
-fn handle_mem_read(args: &[&str]) {
-    if args.is_empty() {
-        print!(("\nMissing memory address"), fg: Color::Red);
-        print_help();
-        return;
+fn handle_translate_command(args: &Args) -> Result<(), CommandError> {
+    args.expect_at_most(1)?;
+    let addr: u64 = args.number(0, "address")?;
+    let mapping = paging::walk(addr).map_err(|error| args.invalid(0, error.reason()))?;
+
+    let writable = mapping.flags.contains(PageTableFlags::WRITABLE);
+    let executable = !mapping.flags.contains(PageTableFlags::NO_EXECUTE);
+    print!(("\n0x{:016X} -> 0x{:012X}", addr, mapping.phys.as_u64()), fg: Color::LightGreen);
+    print!(("\n  Page size: {} KiB", mapping.page_size / 1024), fg: Color::White);
+    print!(("\n  Access:    {}{}", if writable { "read/write" } else { "read-only" }, if executable { ", executable" } else { ", no-execute" }), fg: Color::White);
+    print!(("\n  Flags:     {:?}", mapping.flags), fg: Color::White);
+    Ok(())
+}
+
+/// Флаги записи таблицы страниц в порядке битов.
+const ENTRY_FLAGS: [(PageTableFlags, &str); 10] = [
+    (PageTableFlags::PRESENT, "P"),
+    (PageTableFlags::WRITABLE, "RW"),
+    (PageTableFlags::USER_ACCESSIBLE, "US"),
+    (PageTableFlags::WRITE_THROUGH, "PWT"),
+    (PageTableFlags::NO_CACHE, "PCD"),
+    (PageTableFlags::ACCESSED, "A"),
+    (PageTableFlags::DIRTY, "D"),
+    (PageTableFlags::HUGE_PAGE, "PS"),
+    (PageTableFlags::GLOBAL, "G"),
+    (PageTableFlags::NO_EXECUTE, "NX"),
+];
+
+// В сводке A и D не показываем: процессор ставит их сам, и соседние
+// страницы из-за них не склеивались бы в один диапазон
+const RANGE_FLAGS: PageTableFlags = PageTableFlags::WRITABLE
+    .union(PageTableFlags::USER_ACCESSIBLE)
+    .union(PageTableFlags::WRITE_THROUGH)
+    .union(PageTableFlags::NO_CACHE)
+    .union(PageTableFlags::GLOBAL)
+    .union(PageTableFlags::NO_EXECUTE);
+
+fn print_entry_flags(flags: PageTableFlags) {
+    for (flag, name) in ENTRY_FLAGS {
+        if flags.contains(flag) {
+            print!((" {}", name), fg: Color::White);
+        }
     }
+}
 
-    let addr = match parse_hex_u64(args[0]) {
-        Some(addr) => addr,
+fn handle_pt_command(args: &Args) -> Result<(), CommandError> {
+    args.expect_at_most(1)?;
+    match args.optional_number::<u64>(0)? {
+        Some(addr) => print_page_walk(args, addr),
         None => {
-            print!(("\nInvalid address. Use hex (e.g., 0x1000)"), fg: Color::Red);
-            return;
+            print_mapped_ranges();
+            Ok(())
         }
-    };
+    }
+}
+
+fn print_page_walk(args: &Args, addr: u64) -> Result<(), CommandError> {
+    let entries = paging::table_entries(addr).map_err(|error| args.invalid(0, error.reason()))?;
+    let (cr3, _) = Cr3::read();
 
-    let length = if args.len() > 1 {
-        match args[1].parse::<usize>() {
-            Ok(len) if len > 0 && len <= 64 => len,
-            _ => 16, 
+    print!(("\nPage walk for 0x{:016X} (CR3 = 0x{:X}):", addr, cr3.start_address().as_u64()), fg: Color::LightBlue);
+    for entry in entries.into_iter().flatten() {
+        let name = match entry.level {
+            4 => "PML4",
+            3 => "PDPT",
+            2 => "PD",
+            _ => "PT",
+        };
+        print!(("\n  {:<4}[{:>3}] @0x{:09X}: ", name, entry.index, entry.table.as_u64() + entry.index as u64 * 8), fg: Color::LightCyan);
+        if !entry.flags.contains(PageTableFlags::PRESENT) {
+            print!(("not present"), fg: Color::Yellow);
+            continue;
         }
-    } else {
-        16
-    };
+        print!(("0x{:012X}", entry.addr.as_u64()), fg: Color::White);
+        print_entry_flags(entry.flags);
+    }
+
+    if let Ok(mapping) = paging::walk(addr) {
+        print!(("\n  -> 0x{:012X} in a {} page", mapping.phys.as_u64(), page_size_name(mapping.page_size)), fg: Color::LightGreen);
+    }
+    Ok(())
+}
+
+fn page_size_name(size: u64) -> &'static str {
+    match size {
+        0x1000 => "4K",
+        0x20_0000 => "2M",
+        _ => "1G",
+    }
+}
+
+/// Непрерывный кусок отображения с одинаковыми флагами и размером страниц.
+#[derive(Clone, Copy)]
+struct Range {
+    start: u64,
+    end: u64,
+    phys: u64,
+    page_size: u64,
+    flags: PageTableFlags,
+}
+
+impl Range {
+    /// Продолжает ли страница этот диапазон и по виртуальным, и по физическим адресам.
+    fn extends(&self, virt: u64, phys: u64, page_size: u64, flags: PageTableFlags) -> bool {
+        virt == self.end
+            && phys == self.phys + (self.end - self.start)
+            && page_size == self.page_size
+            && flags == self.flags
+    }
+
+    fn print(&self) {
+        print!(("\n  0x{:016X}-0x{:016X} -> 0x{:012X} ", self.start, self.end, self.phys), fg: Color::White);
+        let access = if self.flags.contains(PageTableFlags::WRITABLE) { "RW" } else { "RO" };
+        print!(("{}", access), fg: Color::LightGreen);
+        for (flag, name) in ENTRY_FLAGS {
+            if flag != PageTableFlags::WRITABLE && RANGE_FLAGS.contains(flag) && self.flags.contains(flag) {
+                print!((" {}", name), fg: Color::White);
+            }
+        }
+        print!((" {}", page_size_name(self.page_size)), fg: Color::LightCyan);
+    }
+}
+
+// Столько готовых диапазонов собирается за один проход под блокировкой таблиц
+const RANGES_PER_WALK: usize = 16;
+
+fn print_mapped_ranges() {
+    print!(("\nMapped ranges:"), fg: Color::LightBlue);
+    let mut pager = Pager::new();
+    let mut current: Option<Range> = None;
+    let mut from = Some(VirtAddr::zero());
 
-    let virt_addr = VirtAddr::new(addr);
-    
-    if !is_address_accessible(virt_addr) {
-        print!(("\nCannot access memory at address 0x{:X}", addr), fg: Color::Red);
+    // Пейджер может ждать клавишу сколько угодно, а таблицы на время обхода
+    // заблокированы: диапазоны собираются пачками и печатаются уже без блокировки
+    while let Some(start) = from.take() {
+        let mut done = [None; RANGES_PER_WALK];
+        let mut count = 0;
+        paging::for_each_mapping(start, |virt, phys, page_size, flags| {
+            let (addr, phys, flags) = (virt.as_u64(), phys.as_u64(), flags & RANGE_FLAGS);
+            if let Some(range) = current.as_mut() {
+                if range.extends(addr, phys, page_size, flags) {
+                    range.end += page_size;
+                    return true;
+                }
+            }
+            if count == RANGES_PER_WALK {
+                from = Some(virt);
+                return false;
+            }
+            let next = Range { start: addr, end: addr + page_size, phys, page_size, flags };
+            if let Some(range) = current.replace(next) {
+                done[count] = Some(range);
+                count += 1;
+            }
+            true
+        });
+
+        for range in done.iter().flatten() {
+            if !pager.next_line() {
+                return;
+            }
+            range.print();
+        }
+    }
+
+    if let Some(range) = current {
+        if pager.next_line() {
+            range.print();
+        }
+    }
+}
+
+fn handle_frames_command(args: &Args) -> Result<(), CommandError> {
+    args.expect_at_most(0)?;
+    let stats = memory::FRAME_ALLOCATOR.lock().stats();
+    let (kernel_start, kernel_end) = memory::kernel_range();
+    let used = stats.usable - stats.free;
+
+    print!(("\nPhysical memory (4 KiB frames):"), fg: Color::LightBlue);
+    print!(("\n  Usable:   {:>8} frames  {:>6} KiB", stats.usable, stats.usable as u64 * FRAME_SIZE / 1024), fg: Color::White);
+    print!(("\n  Free:     {:>8} frames  {:>6} KiB", stats.free, stats.free as u64 * FRAME_SIZE / 1024), fg: Color::LightGreen);
+    print!(("\n  Used:     {:>8} frames  {:>6} KiB", used, used as u64 * FRAME_SIZE / 1024), fg: Color::White);
+    print!(("\n  Reserved: {:>23} KiB", stats.reserved_bytes / 1024), fg: Color::White);
+    if stats.ignored_bytes > 0 {
+        print!(("\n  Above 4 GiB (unused): {} KiB", stats.ignored_bytes / 1024), fg: Color::Yellow);
+    }
+    print!(("\n  Kernel:   0x{:08X}-0x{:08X}", kernel_start.as_u64(), kernel_end.as_u64()), fg: Color::White);
+
+    if args.flag("map") {
+        print_memory_map()?;
+    }
+    Ok(())
+}
+
+fn print_memory_map() -> Result<(), CommandError> {
+    let info = multiboot::info().ok_or(CommandError::Failed("no multiboot2 info"))?;
+    let map = info.memory_map().ok_or(CommandError::Failed("bootloader gave no memory map"))?;
+
+    print!(("\nMemory map from {}:", info.bootloader_name().unwrap_or("the bootloader")), fg: Color::LightBlue);
+    for region in map {
+        let color = if region.kind == multiboot::RegionKind::Available { Color::LightGreen } else { Color::DarkGray };
+        print!(("\n  0x{:012X}-0x{:012X}  {:>9} KiB  {}", region.start, region.end(), region.length / 1024, region.kind.name()), fg: color);
+    }
+    Ok(())
+}
+
+fn handle_heap_command(args: &Args) -> Result<(), CommandError> {
+    args.expect_at_most(0)?;
+    match args.option("track") {
+        Some("on") => allocator::set_tracking(true),
+        Some("off") => allocator::set_tracking(false),
+        Some(_) => return Err(args.invalid_option("track", "expected on or off")),
+        None => {}
+    }
+
+    let stats = allocator::stats();
+    print!(("\nKernel heap at 0x{:X}:", allocator::HEAP_START), fg: Color::LightBlue);
+    print!(("\n  Mapped:  {:>8} KiB (max {} KiB)", stats.mapped / 1024, allocator::HEAP_MAX_SIZE / 1024), fg: Color::White);
+    print!(("\n  In use:  {:>8} bytes", stats.in_use), fg: Color::LightGreen);
+    print!(("\n  Peak:    {:>8} bytes", stats.peak), fg: Color::White);
+    print!(("\n  Allocs:  {:>8}  frees: {}  failed: {}", stats.allocations, stats.frees, stats.failures), fg: Color::White);
+    print!(("\n  Free list: {} bytes, largest block {} bytes, fragmentation {}%",
+        stats.free_list, stats.largest_free, stats.fragmentation()), fg: Color::White);
+    print!(("\n  Cached in size classes: {} bytes", stats.slab_cached), fg: Color::White);
+
+    print!(("\n  Size class     live      total"), fg: Color::LightCyan);
+    for (size, class) in SIZE_CLASSES.iter().zip(&stats.classes) {
+        print!(("\n  {:>10} {:>8} {:>10}", size, class.live, class.total), fg: Color::White);
+    }
+    print!(("\n  {:>10} {:>8} {:>10}", "larger", stats.large.live, stats.large.total), fg: Color::White);
+
+    let tracking = allocator::is_tracking();
+    print!(("\n  Tracking: {}", if tracking { "on" } else { "off" }), fg: if tracking { Color::Yellow } else { Color::DarkGray });
+
+    if args.flag("live") {
+        print_live_allocations(tracking);
+    }
+    Ok(())
+}
+
+fn print_live_allocations(tracking: bool) {
+    if !tracking {
+        print!(("\nTracking is off, enable it with 'mem heap --track on'"), fg: Color::Yellow);
         return;
     }
 
+    print!(("\nLive allocations since tracking was enabled:"), fg: Color::LightBlue);
+    let mut count = 0;
+    // Куча заблокирована, пока идёт обход: здесь ничего не выделяем
+    let dropped = allocator::for_each_record(|record| {
+        count += 1;
+        print!(("\n  #{:<8} 0x{:X} {:>8} bytes  {}", record.sequence, record.addr, record.size, record.tag), fg: Color::White);
+    });
+    if count == 0 {
+        print!(("\n  none"), fg: Color::DarkGray);
+    }
+    if dropped > 0 {
+        print!(("\n  {} more not recorded: the table holds {}", dropped, allocator::TRACK_CAPACITY), fg: Color::Yellow);
+    }
+}
+
+/// Размер элемента из `--type` в байтах.
+fn element_size(args: &Args) -> Result<usize, CommandError> {
+    match args.option("type") {
+        None | Some("u8") => Ok(1),
+        Some("u16") => Ok(2),
+        Some("u32") => Ok(4),
+        Some("u64") => Ok(8),
+        Some(_) => Err(args.invalid_option("type", "type must be u8, u16, u32 or u64")),
+    }
+}
+
+/// Адрес из аргумента `index`, выровненный на размер элемента: регистры
+/// устройств читаются только целиком.
+fn aligned_address(args: &Args, index: usize, name: &'static str, size: usize) -> Result<u64, CommandError> {
+    let addr: u64 = args.number(index, name)?;
+    if !addr.is_multiple_of(size as u64) {
+        return Err(args.invalid(index, "address must be aligned to the element size"));
+    }
+    Ok(addr)
+}
+
+/// Длина в байтах: не ноль и кратна размеру элемента.
+fn range_length(args: &Args, index: usize, size: usize, max: usize) -> Result<usize, CommandError> {
+    let length: usize = args.number(index, "length")?;
+    if length == 0 || length > max {
+        return Err(args.invalid(index, "length is zero or too large"));
+    }
+    if !length.is_multiple_of(size) {
+        return Err(args.invalid(index, "length must be a multiple of the element size"));
+    }
+    Ok(length)
+}
+
+fn check(args: &Args, index: usize, addr: u64, length: usize, write: bool) -> Result<(), CommandError> {
+    paging::check_access(addr, length as u64, write).map_err(|error| args.invalid(index, error.reason()))
+}
+
+/// Читает элемент одним обращением нужной ширины. Значение — в порядке
+/// байт памяти, то есть little-endian.
+unsafe fn read_value(addr: u64, size: usize) -> u64 {
+    match size {
+        1 => (addr as *const u8).read_volatile() as u64,
+        2 => (addr as *const u16).read_volatile() as u64,
+        4 => (addr as *const u32).read_volatile() as u64,
+        _ => (addr as *const u64).read_volatile(),
+    }
+}
+
+unsafe fn write_value(addr: u64, size: usize, value: u64) {
+    match size {
+        1 => (addr as *mut u8).write_volatile(value as u8),
+        2 => (addr as *mut u16).write_volatile(value as u16),
+        4 => (addr as *mut u32).write_volatile(value as u32),
+        _ => (addr as *mut u64).write_volatile(value),
+    }
+}
+
+/// Переставляет байты элемента, если нужен big-endian.
+fn to_order(value: u64, size: usize, big_endian: bool) -> u64 {
+    if big_endian {
+        value.swap_bytes() >> (64 - size * 8)
+    } else {
+        value
+    }
+}
+
+fn handle_mem_read(args: &Args) -> Result<(), CommandError> {
+    args.expect_at_most(2)?;
+    let size = element_size(args)?;
+    let addr = aligned_address(args, 0, "address", size)?;
+    let length = match args.optional_number::<usize>(1)? {
+        Some(_) => range_length(args, 1, size, MAX_DUMP)?,
+        None => 16,
+    };
+    check(args, 0, addr, length, false)?;
+
     print!(("\nMemory at 0x{:016X}: ", addr), fg: Color::LightBlue);
-    
-    for i in 0..length {
-        if i % 16 == 0 {
-            if i > 0 {
-                print!((" |"));
-                for j in (i-16)..i {
-                    if j < length {
-                        let byte = unsafe { read_byte(virt_addr + j as u64) };
-                        if byte.is_ascii_graphic() {
-                            print!(("{}", byte as char), fg: Color::White);
-                        } else {
-                            print!(("."), fg: Color::DarkGray);
-                        }
-                    }
-                }
-                print!(("|"));
+    dump(addr, length, size, args.flag("big-endian"));
+    Ok(())
+}
+
+/// Выводит `length` байт по 16 в строке: элементы размера `size`, затем
+/// те же байты как текст. Каждый элемент читается один раз.
+fn dump(addr: u64, length: usize, size: usize, big_endian: bool) {
+    let digits = size * 2;
+    let mut pager = Pager::new();
+
+    for line in (0..length).step_by(16) {
+        if !pager.next_line() {
+            break;
+        }
+        let count = (length - line).min(16);
+        let mut bytes = [0u8; 16];
+
+        print!(("\n  {:016X}: ", addr + line as u64), fg: Color::LightCyan);
+        for offset in (0..16).step_by(size) {
+            if offset < count {
+                let value = unsafe { read_value(addr + (line + offset) as u64, size) };
+                bytes[offset..offset + size].copy_from_slice(&value.to_le_bytes()[..size]);
+                print!(("{:0digits$X} ", to_order(value, size, big_endian), digits = digits), fg: Color::White);
+            } else {
+                print!(("{:digits$} ", "", digits = digits));
             }
-            print!(("\n  {:016X}: ", addr + i as u64), fg: Color::LightCyan);
-        }
-        
-        if i < length {
-            let byte = unsafe { read_byte(virt_addr + i as u64) };
-            print!(("{:02X} ", byte), fg: Color::White);
-        }
-    }
-    
-    let remaining = length % 16;
-    if remaining > 0 {
-        let padding = (16 - remaining) * 3;
-        for _ in 0..padding { print!((" "), fg: Color::White); }
-        print!((" |"), fg: Color::White);
-        
-        let start = length - remaining;
-        for j in start..length {
-            let byte = unsafe { read_byte(virt_addr + j as u64) };
+        }
+
+        print!((" |"));
+        for &byte in &bytes[..count] {
             if byte.is_ascii_graphic() {
                 print!(("{}", byte as char), fg: Color::White);
             } else {
                 print!(("."), fg: Color::DarkGray);
             }
         }
-        print!(("|"), fg: Color::White);
+        print!(("|"));
     }
 }
 
-fn handle_mem_write(args: &[&str]) {
-    if args.len() < 2 {
-        print!(("\nMissing address or value"), fg: Color::Red);
-        print_help();
-        return;
+fn handle_mem_write(args: &Args) -> Result<(), CommandError> {
+    args.expect_at_most(2)?;
+    let size = element_size(args)?;
+    let addr = aligned_address(args, 0, "address", size)?;
+    let value: u64 = args.number(1, "value")?;
+    if size < 8 && value >> (size * 8) != 0 {
+        return Err(args.invalid(1, "value does not fit the element type"));
     }
+    check(args, 0, addr, size, true)?;
 
-    let addr = match parse_hex_u64(args[0]) {
-        Some(addr) => addr,
-        None => {
-            print!(("\nInvalid address. Use hex (e.g., 0x1000)"), fg: Color::Red);
-            return;
-        }
-    };
-
-    let value = match parse_hex_u8(args[1]) {
-        Some(val) => val,
-        None => {
-            print!(("\nInvalid value. Use hex (e.g., 0x41)"), fg: Color::Red);
-            return;
-        }
+    let big_endian = args.flag("big-endian");
+    let digits = size * 2;
+    let old_value = unsafe {
+        let old_value = read_value(addr, size);
+        write_value(addr, size, to_order(value, size, big_endian));
+        to_order(old_value, size, big_endian)
     };
+    print!(("\nWrote 0x{:0digits$X} to 0x{:016X} (was: 0x{:0digits$X})", value, addr, old_value, digits = digits),
+        fg: Color::LightGreen);
+    Ok(())
+}
 
-    let virt_addr = VirtAddr::new(addr);
-    
-    if !is_address_writable(virt_addr) {
-        print!(("\nCannot write to memory at address 0x{:X}", addr), fg: Color::Red);
-        return;
+fn handle_mem_fill(args: &Args) -> Result<(), CommandError> {
+    args.expect_at_most(3)?;
+    let size = element_size(args)?;
+    let addr = aligned_address(args, 0, "address", size)?;
+    let length = range_length(args, 1, size, usize::MAX)?;
+    let value: u64 = args.number(2, "value")?;
+    if size < 8 && value >> (size * 8) != 0 {
+        return Err(args.invalid(2, "value does not fit the element type"));
     }
-    
-    unsafe {
-        let ptr = virt_addr.as_mut_ptr::<u8>();
-        let old_value = ptr.read_volatile();
-        ptr.write_volatile(value);
-        print!(("\nWrote 0x{:02X} to 0x{:016X} (was: 0x{:02X})", value, addr, old_value), 
-              fg: Color::LightGreen);
+    check(args, 0, addr, length, true)?;
+
+    let value = to_order(value, size, args.flag("big-endian"));
+    for offset in (0..length).step_by(size) {
+        unsafe { write_value(addr + offset as u64, size, value) };
     }
+    print!(("\nFilled {} bytes at 0x{:016X}", length, addr), fg: Color::LightGreen);
+    Ok(())
 }
 
-unsafe fn read_byte(addr: VirtAddr) -> u8 {
-    let ptr = addr.as_ptr::<u8>();
-    ptr.read_volatile()
-}
+fn handle_mem_copy(args: &Args) -> Result<(), CommandError> {
+    args.expect_at_most(3)?;
+    let destination: u64 = args.number(0, "destination")?;
+    let source: u64 = args.number(1, "source")?;
+    let length = range_length(args, 2, 1, usize::MAX)?;
+    check(args, 1, source, length, false)?;
+    check(args, 0, destination, length, true)?;
 
-fn is_address_accessible(addr: VirtAddr) -> bool {
-    // TODO: Слишком базово я думаю
-    !addr.is_null()
+    // Как memmove: диапазоны могут перекрываться
+    unsafe { core::ptr::copy(source as *const u8, destination as *mut u8, length) };
+    print!(("\nCopied {} bytes from 0x{:016X} to 0x{:016X}", length, source, destination), fg: Color::LightGreen);
+    Ok(())
 }
 
-fn is_address_writable(addr: VirtAddr) -> bool {
-    // TODO: Мне не нравится
-    is_address_accessible(addr)
+fn handle_mem_cmp(args: &Args) -> Result<(), CommandError> {
+    args.expect_at_most(3)?;
+    let first: u64 = args.number(0, "first")?;
+    let second: u64 = args.number(1, "second")?;
+    let length = range_length(args, 2, 1, usize::MAX)?;
+    check(args, 0, first, length, false)?;
+    check(args, 1, second, length, false)?;
+
+    let mut pager = Pager::new();
+    let mut listing = true;
+    let mut differences = 0;
+    for offset in 0..length as u64 {
+        let (a, b) = unsafe { (read_value(first + offset, 1), read_value(second + offset, 1)) };
+        if a == b {
+            continue;
+        }
+        differences += 1;
+        // После q только досчитываем
+        listing = listing && pager.next_line();
+        if listing {
+            print!(("\n  +0x{:08X}: {:02X} != {:02X}", offset, a, b), fg: Color::Yellow);
+        }
+    }
+
+    if differences == 0 {
+        print!(("\nRanges are identical ({} bytes)", length), fg: Color::LightGreen);
+    } else {
+        print!(("\n{} of {} bytes differ", differences, length), fg: Color::Yellow);
+    }
+    Ok(())
 }
 
-fn parse_hex_u64(s: &str) -> Option<u64> {
-    u64::from_str_radix(s.trim_start_matches("0x"), 16).ok()
+fn handle_mem_find(args: &Args) -> Result<(), CommandError> {
+    let addr: u64 = args.number(0, "address")?;
+    let length = range_length(args, 1, 1, usize::MAX)?;
+    args.required(2, "pattern")?;
+
+    let mut buffer = [0u8; MAX_PATTERN];
+    let pattern = if args.flag("text") {
+        text_pattern(args, &mut buffer)?
+    } else {
+        hex_pattern(args, &mut buffer)?
+    };
+    if pattern.len() > length {
+        return Err(args.invalid(1, "range is shorter than the pattern"));
+    }
+    check(args, 0, addr, length, false)?;
+
+    let mut pager = Pager::new();
+    let mut matches = 0;
+    for offset in 0..=(length - pattern.len()) as u64 {
+        let start = addr + offset;
+        let found = pattern
+            .iter()
+            .enumerate()
+            .all(|(i, &byte)| unsafe { read_value(start + i as u64, 1) } == byte as u64);
+        if !found {
+            continue;
+        }
+        matches += 1;
+        if !pager.next_line() {
+            break;
+        }
+        print!(("\n  0x{:016X}", start), fg: Color::LightGreen);
+    }
+
+    if matches == 0 {
+        print!(("\nPattern not found"), fg: Color::Yellow);
+    } else {
+        print!(("\n{} match(es)", matches), fg: Color::White);
+    }
+    Ok(())
 }
 
-fn parse_hex_u8(s: &str) -> Option<u8> {
-    u8::from_str_radix(s.trim_start_matches("0x"), 16).ok()
+/// Слова с 2-го как байты в hex: `DE AD BE EF` или `DEADBEEF`, можно с `0x`.
+fn hex_pattern<'b>(args: &Args, buffer: &'b mut [u8; MAX_PATTERN]) -> Result<&'b [u8], CommandError> {
+    let mut len = 0;
+    let mut index = 2;
+    while let Some(word) = args.get(index) {
+        let digits = word.strip_prefix("0x").or_else(|| word.strip_prefix("0X")).unwrap_or(word);
+        if digits.is_empty() || !digits.len().is_multiple_of(2) {
+            return Err(args.invalid(index, "expected whole hex bytes, like DEAD or DE AD"));
+        }
+        for pair in digits.as_bytes().chunks(2) {
+            let pair = core::str::from_utf8(pair).unwrap_or("");
+            let byte = u8::from_str_radix(pair, 16).map_err(|_| args.invalid(index, "not a hex byte"))?;
+            if len == MAX_PATTERN {
+                return Err(args.invalid(index, "pattern is longer than 64 bytes"));
+            }
+            buffer[len] = byte;
+            len += 1;
+        }
+        index += 1;
+    }
+    Ok(&buffer[..len])
 }
 
-fn print_help() {
-    print!(("\nMemory commands:"), fg: Color::LightBlue);
-    print!(("\n  mem read <address> [length] - Read memory at address (default: 16 bytes)"), fg: Color::White);
-    print!(("\n  mem write <address> <value> - Write value to memory at address"), fg: Color::White);
-    print!(("\n  mem --help                 - Show this help message"), fg: Color::White);
+/// Слова с 2-го как текст через один пробел.
+fn text_pattern<'b>(args: &Args, buffer: &'b mut [u8; MAX_PATTERN]) -> Result<&'b [u8], CommandError> {
+    let mut len = 0;
+    let mut index = 2;
+    while let Some(word) = args.get(index) {
+        let separator = if index > 2 { " " } else { "" };
+        for &byte in separator.as_bytes().iter().chain(word.as_bytes()) {
+            if len == MAX_PATTERN {
+                return Err(args.invalid(index, "pattern is longer than 64 bytes"));
+            }
+            buffer[len] = byte;
+            len += 1;
+        }
+        index += 1;
+    }
+    Ok(&buffer[..len])
 }
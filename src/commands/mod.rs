@@ -1,38 +1,182 @@
+pub mod apic;
+mod args;
+mod command;
 mod cpu;
+pub mod date;
 mod disk;
+pub mod kbd;
 mod mem;
-mod pic;
+mod pager;
+pub mod pic;
 mod port;
+pub mod registry;
 mod screen;
+pub mod serial;
 mod system;
+pub mod time;
 
+use crate::allocator;
 use crate::vga_buffer::Color;
 use crate::print;
+use registry::{optional, ArgKind, CommandSpec, Usage};
 
-pub fn handle_command(command: &str, args: &[&str]) {
-    match command {
-        "help" => print_help(),
-        "pic" => pic::handle_pic_command(args),
-        "port" => port::handle_port_command(args),
-        "cpu" => cpu::handle_cpu_command(args),
-        "mem" => mem::handle_mem_command(args),
-        "disk" => disk::handle_disk_command(args),
-        "screen" => screen::handle_screen_command(args),
-        "reboot" => system::reboot(),
-        "shutdown" => system::shutdown(),
-        _ => print!(("\nUnknown command: {}. Type 'help' for available commands.", command), fg: Color::Red),
+pub use args::Args;
+pub use command::{Command, CommandError};
+pub use registry::register;
+
+static HELP: CommandSpec = CommandSpec {
+    name: "help",
+    aliases: &["?"],
+    summary: "Show this help",
+    usage: Usage {
+        subcommands: &[],
+        args: &[optional("command", ArgKind::Dynamic(command_names))],
+    },
+    run: run_help,
+};
+
+// Команды драйверов регистрируют сами драйверы в своих init
+static BUILTIN: [&dyn Command; 8] = [
+    &cpu::COMMAND,
+    &disk::COMMAND,
+    &mem::COMMAND,
+    &port::COMMAND,
+    &screen::COMMAND,
+    &system::REBOOT,
+    &system::SHUTDOWN,
+    &HELP,
+];
+
+pub fn init() {
+    register_all(&BUILTIN);
+}
+
+/// Регистрирует команды подсистемы. Ошибку только печатаем: без своей
+/// команды подсистема всё равно работает.
+pub fn register_all(commands: &[&'static dyn Command]) {
+    for &command in commands {
+        if let Err(error) = register(command) {
+            print!(("\nFailed to register '{}': {:?}", command.name(), error), fg: Color::Red);
+        }
     }
 }
 
-fn print_help() {
+fn command_names(out: &mut dyn FnMut(&'static str)) {
+    registry::for_each(|command| out(command.name()));
+}
+
+pub fn handle_command(name: &str, words: &[&str]) {
+    let Some(command) = registry::find(name) else {
+        print!(("\nUnknown command: {}. Type 'help' for available commands.", name), fg: Color::Red);
+        return;
+    };
+
+    if matches!(words.first(), Some(&("--help" | "-h"))) {
+        registry::print_help(command);
+        return;
+    }
+
+    let args = Args::new(words, command.usage().options(words));
+    // Метка для журнала кучи: по ней видно, какая команда оставила выделения
+    let result = allocator::with_tag(command.name(), || args.check_options().and_then(|_| command.run(&args)));
+    if let Err(error) = result {
+        report(command, &args, error);
+    }
+}
+
+fn report(command: &dyn Command, args: &Args, error: CommandError) {
+    let name = command.name();
+    let token = |index: usize| args.word(index).unwrap_or("");
+
+    match error {
+        CommandError::MissingSubcommand => registry::print_help(command),
+        CommandError::UnknownSubcommand { index } => {
+            print!(("\nUnknown {} subcommand '{}'", name, token(index)), fg: Color::Red);
+        }
+        CommandError::MissingArgument(argument) => {
+            print!(("\nMissing argument: {}", argument), fg: Color::Red);
+        }
+        CommandError::InvalidArgument { index, reason } => {
+            print!(("\nInvalid argument '{}': {}", token(index), reason), fg: Color::Red);
+        }
+        CommandError::UnexpectedArgument { index } => {
+            print!(("\nUnexpected argument '{}'", token(index)), fg: Color::Red);
+        }
+        CommandError::UnknownOption { index } => {
+            print!(("\nUnknown option '{}'", token(index)), fg: Color::Red);
+        }
+        CommandError::MissingOptionValue { index } => {
+            print!(("\nOption '{}' needs a value", token(index)), fg: Color::Red);
+        }
+        CommandError::Failed(message) => print!(("\nError: {}", message), fg: Color::Red),
+    }
+
+    if let Some(index) = error_index(error) {
+        highlight(name, args.words(), index);
+    }
+    if error != CommandError::MissingSubcommand {
+        print!(("\nType '{} --help' for usage.", name), fg: Color::LightGray);
+    }
+}
+
+fn error_index(error: CommandError) -> Option<usize> {
+    match error {
+        CommandError::UnknownSubcommand { index }
+        | CommandError::InvalidArgument { index, .. }
+        | CommandError::UnexpectedArgument { index }
+        | CommandError::UnknownOption { index }
+        | CommandError::MissingOptionValue { index } => Some(index),
+        _ => None,
+    }
+}
+
+/// Повторяет строку команды и подчёркивает слово `index`.
+fn highlight(name: &str, words: &[&str], index: usize) {
+    print!(("\n  {}", name), fg: Color::LightGray);
+    let mut column = name.chars().count() + 1;
+    for (i, word) in words.iter().enumerate() {
+        if i == index {
+            print!((" {}", word), fg: Color::Yellow);
+        } else {
+            print!((" {}", word), fg: Color::LightGray);
+        }
+        if i < index {
+            column += word.chars().count() + 1;
+        }
+    }
+
+    // За концом строки подчёркиваем место, где не хватило слова
+    let width = words.get(index).map_or(1, |word| word.chars().count());
+    print!(("\n  {:1$}", "", column), fg: Color::Yellow);
+    for _ in 0..width {
+        print!(("^"), fg: Color::Yellow);
+    }
+}
+
+/// Варианты для следующего слова после уже введённых `words`.
+pub fn complete(words: &[&str], out: &mut dyn FnMut(&'static str)) {
+    match words.split_first() {
+        None => command_names(out),
+        Some((name, rest)) => {
+            if let Some(command) = registry::find(name) {
+                command.usage().complete(rest, out);
+            }
+        }
+    }
+}
+
+fn run_help(args: &Args) -> Result<(), CommandError> {
+    args.expect_at_most(1)?;
+    if let Some(name) = args.first() {
+        let command = registry::find(name).ok_or(args.invalid(0, "no such command"))?;
+        registry::print_help(command);
+        return Ok(());
+    }
+
     print!(("\nAvailable commands:"), fg: Color::LightBlue);
-    print!(("\n  cpu     - CPU information and control"), fg: Color::White);
-    print!(("\n  disk    - Disk operations and information"), fg: Color::White);
-    print!(("\n  mem     - Memory operations"), fg: Color::White);
-    print!(("\n  pic     - Programmable Interrupt Controller control"), fg: Color::White);
-    print!(("\n  port    - Port I/O operations"), fg: Color::White);
-    print!(("\n  screen  - Screen control"), fg: Color::White);
-    print!(("\n  reboot  - Reboot the system"), fg: Color::White);
-    print!(("\n  shutdown - Shut down the system"), fg: Color::White);
-    print!(("\n  help    - Show this help"), fg: Color::White);
+    registry::for_each(|command| {
+        print!(("\n  {:<8} - {}", command.name(), command.summary()), fg: Color::White);
+    });
+    print!(("\nType '<command> --help' for details, Tab completes commands"), fg: Color::LightGray);
+    Ok(())
 }
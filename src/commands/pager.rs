@@ -0,0 +1,67 @@
+//! Постраничный вывод для команд с длинной выдачей.
+//!
+//! Команда выполняется в главном цикле, поэтому пока она ждёт нажатия,
+//! очереди клавиатуры и COM1 никто другой не читает. Ждём клавишу с той
+//! консоли, куда идёт вывод.
+
+use x86_64::instructions::interrupts;
+use crate::console::{self, Output};
+use crate::drivers::keyboard::{self, KeyCode};
+use crate::drivers::serial;
+use crate::vga_buffer::Color;
+use crate::print;
+
+// На экране 25 строк: оставляем место под приглашение и начало страницы
+const PAGE_LINES: usize = 22;
+const CTRL_C: u8 = 0x03;
+
+pub struct Pager {
+    lines: usize,
+}
+
+impl Pager {
+    pub const fn new() -> Self {
+        Self { lines: 0 }
+    }
+
+    /// Вызывается перед каждой строкой. `false` — пользователь прервал вывод.
+    pub fn next_line(&mut self) -> bool {
+        if self.lines < PAGE_LINES {
+            self.lines += 1;
+            return true;
+        }
+        print!(("\n-- more: any key to continue, q to stop --"), fg: Color::Black, bg: Color::LightGray);
+        self.lines = 1;
+        !wait_key_is_quit()
+    }
+}
+
+fn wait_key_is_quit() -> bool {
+    let output = console::output();
+    loop {
+        if output != Output::Serial {
+            while let Some(event) = keyboard::read_event() {
+                if event.pressed {
+                    return matches!(event.key, KeyCode::Q | KeyCode::Escape)
+                        || (event.key == KeyCode::C && event.modifiers.ctrl());
+                }
+            }
+        }
+        if output != Output::Screen {
+            if let Some(byte) = serial::read_byte() {
+                return matches!(byte, b'q' | b'Q' | CTRL_C);
+            }
+        }
+        // Как в главном цикле: без окна между проверкой и hlt
+        // Смотрим только читаемые консоли: байт в чужой очереди не разбудил
+        // бы цикл и не дал бы уснуть
+        interrupts::disable();
+        let pending = (output != Output::Serial && keyboard::has_input())
+            || (output != Output::Screen && serial::has_input());
+        if pending {
+            interrupts::enable();
+        } else {
+            interrupts::enable_and_hlt();
+        }
+    }
+}
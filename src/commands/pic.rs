@@ -1,20 +1,35 @@
-use crate::drivers::pic;
+use crate::drivers::{apic, pic};
 use crate::vga_buffer::Color;
 use crate::print;
+use super::registry::{arg, optional, sub, ArgKind, CommandSpec, Usage};
+use super::{Args, CommandError};
 
-pub fn handle_pic_command(args: &[&str]) {
-    if args.is_empty() || args[0] == "--help" {
-        print_help();
-        return;
-    }
+pub static COMMAND: CommandSpec = CommandSpec {
+    name: "pic",
+    aliases: &[],
+    summary: "Programmable Interrupt Controller control",
+    usage: Usage {
+        subcommands: &[
+            sub("test", &[], "Test PIC functionality"),
+            sub("mask", &[optional("pic", ArgKind::Choice(&["master", "slave"])), optional("value", ArgKind::Number)], "Get or set interrupt masks"),
+            sub("eoi", &[arg("irq", ArgKind::Number)], "Send End of Interrupt for an IRQ"),
+            sub("status", &[], "Show PIC status"),
+        ],
+        args: &[],
+    },
+    run: run_pic_command,
+};
 
-    match args[0] {
-        "test" => test_pic(),
-        "mask" => handle_mask_command(&args[1..]),
-        "eoi" => handle_eoi_command(&args[1..]),
-        "status" => show_status(),
-        _ => print!(("\nUnknown PIC command. Type 'pic --help' for usage."), fg: Color::Red),
+fn run_pic_command(args: &Args) -> Result<(), CommandError> {
+    match args.first() {
+        None => return Err(CommandError::MissingSubcommand),
+        Some("test") => test_pic(),
+        Some("mask") => return handle_mask_command(&args.rest()),
+        Some("eoi") => return handle_eoi_command(&args.rest()),
+        Some("status") => show_status(),
+        Some(_) => return Err(args.unknown_subcommand()),
     }
+    Ok(())
 }
 
 fn test_pic() {
@@ -26,29 +41,22 @@ fn test_pic() {
     }
 }
 
-fn handle_mask_command(args: &[&str]) {
+fn handle_mask_command(args: &Args) -> Result<(), CommandError> {
+    args.expect_at_most(2)?;
     if args.is_empty() {
         let (master_mask, slave_mask) = pic::get_masks();
         print!(("\nMaster PIC mask: 0x{:02X}", master_mask), fg: Color::LightCyan);
         print!(("\nSlave PIC mask: 0x{:02X}", slave_mask), fg: Color::LightCyan);
-        return;
+        return Ok(());
     }
 
-    if args.len() < 2 {
-        print!(("\nMissing value for mask command"), fg: Color::Red);
-        print_help();
-        return;
+    let pic_type = args.required(0, "pic")?;
+    let value: u8 = args.number(1, "value")?;
+    // Пара 8259 замаскирована целиком, открыть линию на ней нельзя
+    if apic::is_active() {
+        return Err(CommandError::Failed("IRQs are routed through the I/O APIC"));
     }
 
-    let pic_type = args[0];
-    let value = match u8::from_str_radix(args[1].trim_start_matches("0x"), 16) {
-        Ok(v) => v,
-        Err(_) => {
-            print!(("\nInvalid mask value. Use hex (e.g., 0x20)"), fg: Color::Red);
-            return;
-        }
-    };
-
     match pic_type {
         "master" => {
             pic::set_master_mask(value);
@@ -58,33 +66,32 @@ fn handle_mask_command(args: &[&str]) {
             pic::set_slave_mask(value);
             print!(("\nSlave PIC mask set to 0x{:02X}", value), fg: Color::LightGreen);
         }
-        _ => print!(("\nInvalid PIC type. Use 'master' or 'slave'"), fg: Color::Red),
+        _ => return Err(args.invalid(0, "use 'master' or 'slave'")),
     }
+    Ok(())
 }
 
-fn handle_eoi_command(args: &[&str]) {
-    if args.is_empty() {
-        print!(("\nMissing IRQ number for EOI command"), fg: Color::Red);
-        print_help();
-        return;
+fn handle_eoi_command(args: &Args) -> Result<(), CommandError> {
+    args.expect_at_most(1)?;
+    let irq: u8 = args.number(0, "irq")?;
+    if irq >= 16 {
+        return Err(args.invalid(0, "IRQ number must be 0-15"));
     }
 
-    let irq = match args[0].parse::<u8>() {
-        Ok(irq) if irq < 16 => irq,
-        _ => {
-            print!(("\nInvalid IRQ number. Must be 0-15"), fg: Color::Red);
-            return;
-        }
-    };
-
-    pic::send_eoi(irq);
-    
-    print!(("\nSent EOI for IRQ {}", irq), fg: Color::LightGreen);
+    if pic::send_eoi(irq) {
+        print!(("\nSent EOI for IRQ {}", irq), fg: Color::LightGreen);
+    } else {
+        print!(("\nIRQ {} is not in service, EOI suppressed as for a spurious interrupt", irq), fg: Color::Yellow);
+    }
+    Ok(())
 }
 
 fn show_status() {
     let (master_mask, slave_mask) = pic::get_masks();
-    
+    let (master_isr, slave_isr) = pic::get_isr();
+    let (master_irr, slave_irr) = pic::get_irr();
+    let (master_spurious, slave_spurious) = pic::spurious_counts();
+
     print!(("\nPIC Status:"), fg: Color::LightBlue);
     print!(("\n  Master PIC mask: 0x{:02X}", master_mask), fg: Color::White);
     print!(("\n  Slave PIC mask:  0x{:02X}", slave_mask), fg: Color::White);
@@ -98,13 +105,26 @@ fn show_status() {
     for i in 0..8 {
         print!(("{}", if slave_mask & (1 << i) != 0 { '1' } else { '0' }));
     }
+
+    print!(("\nIn service / requested (ISR / IRR, bit 0 first):"), fg: Color::LightBlue);
+    print!(("\n  Master (0-7):  "), fg: Color::White);
+    print_bits(master_isr);
+    print!((" / "), fg: Color::DarkGray);
+    print_bits(master_irr);
+    print!(("\n  Slave (8-15):  "), fg: Color::White);
+    print_bits(slave_isr);
+    print!((" / "), fg: Color::DarkGray);
+    print_bits(slave_irr);
+
+    print!(("\nSpurious interrupts: "), fg: Color::LightBlue);
+    print!(("IRQ7 {}, IRQ15 {}", master_spurious, slave_spurious), fg: Color::White);
+    if apic::is_active() {
+        print!(("\n8259 is masked, IRQs are delivered by the I/O APIC"), fg: Color::DarkGray);
+    }
 }
 
-fn print_help() {
-    print!(("\nPIC (Programmable Interrupt Controller) commands:"), fg: Color::LightBlue);
-    print!(("\n  pic test                - Test PIC functionality"), fg: Color::White);
-    print!(("\n  pic mask [master|slave] [value] - Get or set interrupt masks"), fg: Color::White);
-    print!(("\n  pic eoi <irq>           - Send End of Interrupt for a specific IRQ"), fg: Color::White);
-    print!(("\n  pic status              - Show PIC status"), fg: Color::White);
-    print!(("\n  pic --help             - Show this help message"), fg: Color::White);
+fn print_bits(value: u8) {
+    for i in 0..8 {
+        print!(("{}", if value & (1 << i) != 0 { '1' } else { '0' }));
+    }
 }
@@ -1,78 +1,95 @@
 use x86_64::instructions::port::Port;
 use crate::vga_buffer::Color;
 use crate::print;
+use super::registry::{arg, option, sub, ArgKind, CommandSpec, OptionSpec, Usage};
+use super::{Args, CommandError};
 
-pub fn handle_port_command(args: &[&str]) {
-    if args.is_empty() || args[0] == "--help" {
-        print_help();
-        return;
-    }
+pub static COMMAND: CommandSpec = CommandSpec {
+    name: "port",
+    aliases: &[],
+    summary: "Port I/O operations",
+    usage: Usage {
+        subcommands: &[
+            sub("read", &[arg("address", ArgKind::Address)], "Read from an I/O port")
+                .with_options(&WIDTH_OPTION),
+            sub("write", &[arg("address", ArgKind::Address), arg("value", ArgKind::Number)], "Write to an I/O port")
+                .with_options(&WIDTH_OPTION),
+            sub("list", &[], "List common I/O ports"),
+        ],
+        args: &[],
+    },
+    run: run_port_command,
+};
+
+static WIDTH_OPTION: [OptionSpec; 1] = [
+    option("width", 'w', arg("bits", ArgKind::Choice(&["8", "16", "32"])), "Access width (default: 8)"),
+];
 
-    match args[0] {
-        "read" => handle_port_read(&args[1..]),
-        "write" => handle_port_write(&args[1..]),
-        "list" => list_ports(),
-        _ => print!(("\nUnknown port command. Type 'port --help' for usage."), fg: Color::Red),
+/// Ширина доступа из `--width`.
+fn access_width(args: &Args) -> Result<u32, CommandError> {
+    match args.option_number::<u32>("width")? {
+        None => Ok(8),
+        Some(bits @ (8 | 16 | 32)) => Ok(bits),
+        Some(_) => Err(args.invalid_option("width", "width must be 8, 16 or 32")),
     }
 }
 
-fn handle_port_read(args: &[&str]) {
-    if args.is_empty() {
-        print!(("\nMissing port address"), fg: Color::Red);
-        print_help();
-        return;
+fn run_port_command(args: &Args) -> Result<(), CommandError> {
+    match args.first() {
+        None => Err(CommandError::MissingSubcommand),
+        Some("read") => handle_port_read(&args.rest()),
+        Some("write") => handle_port_write(&args.rest()),
+        Some("list") => {
+            list_ports();
+            Ok(())
+        }
+        Some(_) => Err(args.unknown_subcommand()),
     }
+}
 
-    let port_addr = match parse_hex_u16(args[0]) {
-        Some(addr) => addr,
-        None => {
-            print!(("\nInvalid port address. Use hex (e.g., 0x60)"), fg: Color::Red);
-            return;
-        }
-    };
+fn handle_port_read(args: &Args) -> Result<(), CommandError> {
+    args.expect_at_most(1)?;
+    let port_addr: u16 = args.number(0, "address")?;
+    let width = access_width(args)?;
 
     // Safety: Reading from I/O ports is inherently unsafe
     let value = unsafe {
-        let mut port = Port::<u8>::new(port_addr);
-        port.read()
+        match width {
+            8 => Port::<u8>::new(port_addr).read() as u32,
+            16 => Port::<u16>::new(port_addr).read() as u32,
+            _ => Port::<u32>::new(port_addr).read(),
+        }
     };
 
-    print!(("\nPort 0x{:04X}: 0x{:02X} ({} dec)", port_addr, value, value), fg: Color::LightGreen);
+    let digits = (width / 4) as usize;
+    print!(("\nPort 0x{:04X}: 0x{:0digits$X} ({} dec)", port_addr, value, value, digits = digits), fg: Color::LightGreen);
+    Ok(())
 }
 
-fn handle_port_write(args: &[&str]) {
-    if args.len() < 2 {
-        print!(("\nMissing port address or value"), fg: Color::Red);
-        print_help();
-        return;
+fn handle_port_write(args: &Args) -> Result<(), CommandError> {
+    args.expect_at_most(2)?;
+    let port_addr: u16 = args.number(0, "address")?;
+    let value: u32 = args.number(1, "value")?;
+    let width = access_width(args)?;
+    if width < 32 && value >> width != 0 {
+        return Err(args.invalid(1, "value does not fit the access width"));
     }
 
-    let port_addr = match parse_hex_u16(args[0]) {
-        Some(addr) => addr,
-        None => {
-            print!(("\nInvalid port address. Use hex (e.g., 0x60)"), fg: Color::Red);
-            return;
-        }
-    };
-
-    let value = match parse_hex_u8(args[1]) {
-        Some(val) => val,
-        None => {
-            print!(("\nInvalid value. Use hex (e.g., 0x20)"), fg: Color::Red);
-            return;
-        }
-    };
-
     // Safety: Writing to I/O ports is inherently unsafe
     unsafe {
-        let mut port = Port::<u8>::new(port_addr);
-        port.write(value);
+        match width {
+            8 => Port::<u8>::new(port_addr).write(value as u8),
+            16 => Port::<u16>::new(port_addr).write(value as u16),
+            _ => Port::<u32>::new(port_addr).write(value),
+        }
     }
 
+    let digits = (width / 4) as usize;
     print!(
-        ("\nWrote 0x{:02X} to port 0x{:04X}", value, port_addr),
+        ("\nWrote 0x{:0digits$X} to port 0x{:04X}", value, port_addr, digits = digits),
         fg: Color::LightGreen
     );
+    Ok(())
 }
 
 fn list_ports() {
@@ -87,19 +104,3 @@ fn list_ports() {
     print!(("\n  0x170-0x177 - Secondary IDE Controller"));
     print!(("\n  0x3C0-0x3DF - VGA"));
 }
-
-fn parse_hex_u16(s: &str) -> Option<u16> {
-    u16::from_str_radix(s.trim_start_matches("0x"), 16).ok()
-}
-
-fn parse_hex_u8(s: &str) -> Option<u8> {
-    u8::from_str_radix(s.trim_start_matches("0x"), 16).ok()
-}
-
-fn print_help() {
-    print!(("\nPort I/O commands:"), fg: Color::LightBlue);
-    print!(("\n  port read <address>     - Read value from an I/O port"), fg: Color::White);
-    print!(("\n  port write <address> <value> - Write value to an I/O port"), fg: Color::White);
-    print!(("\n  port list              - List common I/O ports"), fg: Color::White);
-    print!(("\n  port --help            - Show this help message"), fg: Color::White);
-}
@@ -0,0 +1,309 @@
+//! Реестр команд шелла и их описания: подкоманды и аргументы.
+//!
+//! По описаниям работают справка и автодополнение по Tab, поэтому новая
+//! подкоманда должна появиться в `Usage` вместе с обработчиком. Драйверы
+//! регистрируют свои команды через `register` при инициализации.
+
+use crate::vga_buffer::Color;
+use crate::print;
+use crate::spin::SpinMutex;
+use super::args::{self, Args};
+use super::command::{Command, CommandError};
+
+pub enum ArgKind {
+    Number,
+    Address,
+    Text,
+    /// Один из фиксированного набора вариантов.
+    Choice(&'static [&'static str]),
+    /// Варианты известны только во время работы (раскладки, шрифты).
+    Dynamic(fn(&mut dyn FnMut(&'static str))),
+}
+
+pub struct ArgSpec {
+    pub name: &'static str,
+    pub kind: ArgKind,
+    pub optional: bool,
+}
+
+/// Опция `--long` / `-s`: флаг или, если есть `value`, опция со значением.
+pub struct OptionSpec {
+    pub long: &'static str,
+    pub short: char,
+    pub value: Option<ArgSpec>,
+    pub summary: &'static str,
+}
+
+pub struct SubcommandSpec {
+    pub name: &'static str,
+    pub args: &'static [ArgSpec],
+    pub options: &'static [OptionSpec],
+    pub summary: &'static str,
+}
+
+pub struct Usage {
+    pub subcommands: &'static [SubcommandSpec],
+    /// Аргументы самой команды, если подкоманд у неё нет.
+    pub args: &'static [ArgSpec],
+}
+
+/// Команда, целиком описанная данными: подходит почти для всех встроенных.
+pub struct CommandSpec {
+    pub name: &'static str,
+    pub aliases: &'static [&'static str],
+    pub summary: &'static str,
+    pub usage: Usage,
+    pub run: fn(&Args) -> Result<(), CommandError>,
+}
+
+impl Command for CommandSpec {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn aliases(&self) -> &'static [&'static str] {
+        self.aliases
+    }
+
+    fn summary(&self) -> &'static str {
+        self.summary
+    }
+
+    fn usage(&self) -> &Usage {
+        &self.usage
+    }
+
+    fn run(&self, args: &Args) -> Result<(), CommandError> {
+        (self.run)(args)
+    }
+}
+
+pub const fn arg(name: &'static str, kind: ArgKind) -> ArgSpec {
+    ArgSpec { name, kind, optional: false }
+}
+
+pub const fn optional(name: &'static str, kind: ArgKind) -> ArgSpec {
+    ArgSpec { name, kind, optional: true }
+}
+
+pub const fn sub(name: &'static str, args: &'static [ArgSpec], summary: &'static str) -> SubcommandSpec {
+    SubcommandSpec { name, args, options: &[], summary }
+}
+
+pub const fn flag(long: &'static str, short: char, summary: &'static str) -> OptionSpec {
+    OptionSpec { long, short, value: None, summary }
+}
+
+pub const fn option(long: &'static str, short: char, value: ArgSpec, summary: &'static str) -> OptionSpec {
+    OptionSpec { long, short, value: Some(value), summary }
+}
+
+impl SubcommandSpec {
+    pub const fn with_options(self, options: &'static [OptionSpec]) -> Self {
+        Self { options, ..self }
+    }
+}
+
+const USAGE_WIDTH: usize = 32;
+const MAX_INLINE_CHOICES: usize = 3;
+
+impl ArgKind {
+    /// Перебирает варианты значения, если они вообще известны.
+    pub fn candidates(&self, out: &mut dyn FnMut(&'static str)) {
+        match self {
+            ArgKind::Choice(values) => {
+                for value in values.iter() {
+                    out(value);
+                }
+            }
+            ArgKind::Dynamic(list) => list(out),
+            ArgKind::Number | ArgKind::Address | ArgKind::Text => {}
+        }
+    }
+}
+
+impl Usage {
+    pub fn subcommand(&self, name: &str) -> Option<&'static SubcommandSpec> {
+        self.subcommands.iter().find(|sub| sub.name == name)
+    }
+
+    /// Опции подкоманды, с которой начинается строка аргументов.
+    pub fn options(&self, words: &[&str]) -> &'static [OptionSpec] {
+        words
+            .first()
+            .and_then(|name| self.subcommand(name))
+            .map_or(&[], |sub| sub.options)
+    }
+
+    /// Варианты для слова, идущего после уже введённых `words` (без имени команды).
+    pub fn complete(&self, words: &[&str], out: &mut dyn FnMut(&'static str)) {
+        let (specs, options, words) = if self.subcommands.is_empty() {
+            (self.args, &[][..], words)
+        } else {
+            let Some((name, rest)) = words.split_first() else {
+                for sub in self.subcommands.iter() {
+                    out(sub.name);
+                }
+                return;
+            };
+            let Some(sub) = self.subcommand(name) else {
+                return;
+            };
+            (sub.args, sub.options, rest)
+        };
+
+        // Опции и их значения не занимают позиций
+        let mut position = 0;
+        let mut value = None;
+        for word in words.iter() {
+            if value.take().is_some() {
+                continue;
+            }
+            if args::is_option(word) {
+                value = args::option_value(options, word);
+            } else {
+                position += 1;
+            }
+        }
+
+        if let Some(spec) = value.or(specs.get(position)) {
+            spec.kind.candidates(out);
+        }
+    }
+}
+
+const MAX_COMMANDS: usize = 32;
+
+struct Registry {
+    commands: [Option<&'static dyn Command>; MAX_COMMANDS],
+    count: usize,
+}
+
+static REGISTRY: SpinMutex<Registry> = SpinMutex::new(Registry {
+    commands: [None; MAX_COMMANDS],
+    count: 0,
+});
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegisterError {
+    Full,
+    /// Имя или псевдоним уже занят.
+    Duplicate(&'static str),
+}
+
+fn matches(command: &dyn Command, name: &str) -> bool {
+    command.name() == name || command.aliases().contains(&name)
+}
+
+pub fn register(command: &'static dyn Command) -> Result<(), RegisterError> {
+    let mut registry = REGISTRY.lock();
+    let registered = &registry.commands[..registry.count];
+
+    for &existing in registered.iter().flatten() {
+        if matches(existing, command.name()) {
+            return Err(RegisterError::Duplicate(command.name()));
+        }
+        if let Some(&alias) = command.aliases().iter().find(|alias| matches(existing, alias)) {
+            return Err(RegisterError::Duplicate(alias));
+        }
+    }
+
+    if registry.count == MAX_COMMANDS {
+        return Err(RegisterError::Full);
+    }
+    let index = registry.count;
+    registry.commands[index] = Some(command);
+    registry.count += 1;
+    Ok(())
+}
+
+/// Ищет по имени или псевдониму. Реестр при этом не остаётся заблокированным,
+/// так что команда может сама перебирать реестр (как `help`).
+pub fn find(name: &str) -> Option<&'static dyn Command> {
+    let registry = REGISTRY.lock();
+    registry.commands[..registry.count]
+        .iter()
+        .flatten()
+        .copied()
+        .find(|command| matches(*command, name))
+}
+
+/// Перебирает команды в порядке регистрации.
+pub fn for_each(mut f: impl FnMut(&'static dyn Command)) {
+    let registry = REGISTRY.lock();
+    for &command in registry.commands[..registry.count].iter().flatten() {
+        f(command);
+    }
+}
+
+pub fn print_help(command: &dyn Command) {
+    let usage = command.usage();
+    let name = command.name();
+
+    print!(("\n{}:", command.summary()), fg: Color::LightBlue);
+    if usage.subcommands.is_empty() {
+        print_usage(name, "", usage.args, "Run the command");
+    }
+    for sub in usage.subcommands.iter() {
+        print_usage(name, sub.name, sub.args, sub.summary);
+        for option in sub.options.iter() {
+            print_option(option);
+        }
+    }
+    print_usage(name, "--help", &[], "Show this help message");
+
+    if !command.aliases().is_empty() {
+        print!(("\nAliases:"), fg: Color::LightBlue);
+        for alias in command.aliases().iter() {
+            print!((" {}", alias), fg: Color::White);
+        }
+    }
+}
+
+fn print_option(option: &OptionSpec) {
+    print!(("\n      -{}, --{}", option.short, option.long), fg: Color::LightGray);
+    let mut width = option.long.len() + 10;
+    if let Some(value) = &option.value {
+        print!((" <{}>", value.name), fg: Color::LightGray);
+        width += value.name.len() + 3;
+    }
+    let padding = USAGE_WIDTH.saturating_sub(width);
+    print!(("{:2$} - {}", "", option.summary, padding), fg: Color::LightGray);
+}
+
+fn print_usage(command: &str, subcommand: &str, args: &[ArgSpec], summary: &str) {
+    let mut width = command.len();
+    print!(("\n  {}", command), fg: Color::White);
+    if !subcommand.is_empty() {
+        print!((" {}", subcommand), fg: Color::White);
+        width += subcommand.len() + 1;
+    }
+
+    for spec in args.iter() {
+        let (open, close) = if spec.optional { ('[', ']') } else { ('<', '>') };
+        print!((" {}", open), fg: Color::White);
+        width += 2;
+        match spec.kind {
+            // Длинные списки (цвета) в строку не влезут, их показывает Tab
+            ArgKind::Choice(values) if values.len() <= MAX_INLINE_CHOICES => {
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        print!(("|"), fg: Color::White);
+                        width += 1;
+                    }
+                    print!(("{}", value), fg: Color::White);
+                    width += value.len();
+                }
+            }
+            _ => {
+                print!(("{}", spec.name), fg: Color::White);
+                width += spec.name.len();
+            }
+        }
+        print!(("{}", close), fg: Color::White);
+        width += 1;
+    }
+
+    let padding = USAGE_WIDTH.saturating_sub(width);
+    print!(("{:2$} - {}", "", summary, padding), fg: Color::White);
+}
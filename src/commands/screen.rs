@@ -1,18 +1,47 @@
-use crate::vga_buffer::{Color};
+use crate::vga_buffer::{self, Color};
 use crate::print;
+use super::registry::{self, arg, optional, sub, ArgKind, CommandSpec, Usage};
+use super::{Args, CommandError};
 
-pub fn handle_screen_command(args: &[&str]) {
-    if args.is_empty() || args[0] == "--help" {
-        print_help();
-        return;
+const COLOR_NAMES: [&str; 16] = [
+    "black", "blue", "green", "cyan", "red", "magenta", "brown", "light_gray",
+    "dark_gray", "light_blue", "light_green", "light_cyan", "light_red", "pink", "yellow", "white",
+];
+
+pub static COMMAND: CommandSpec = CommandSpec {
+    name: "screen",
+    aliases: &[],
+    summary: "Screen control",
+    usage: Usage {
+        subcommands: &[
+            sub("info", &[], "Show screen status"),
+            sub("set", &[arg("target", ArgKind::Choice(&["fg", "bg"])), arg("color", ArgKind::Choice(&COLOR_NAMES))], "Set text colors"),
+            sub("clear", &[], "Clear the screen"),
+            sub("font", &[optional("name", ArgKind::Dynamic(font_names))], "Show or load a font"),
+        ],
+        args: &[],
+    },
+    run: run_screen_command,
+};
+
+fn font_names(out: &mut dyn FnMut(&'static str)) {
+    out("bios");
+    for font in vga_buffer::FONTS.iter() {
+        out(font.name);
     }
+}
 
-    match args[0] {
-        "info" => show_screen_info(),
-        "set" => handle_set_command(&args[1..]),
-        "clear" => clear_screen(),
-        _ => print!(("\nUnknown screen command. Type 'screen --help' for usage."), fg: Color::Red),
+fn run_screen_command(args: &Args) -> Result<(), CommandError> {
+    match args.first() {
+        // Без подкоманды показываем справку вместе с палитрой
+        None => print_help(),
+        Some("info") => show_screen_info(),
+        Some("set") => handle_set_command(&args.rest()),
+        Some("clear") => clear_screen(),
+        Some("font") => return handle_font_command(&args.rest()),
+        Some(_) => return Err(args.unknown_subcommand()),
     }
+    Ok(())
 }
 
 fn show_screen_info() {
@@ -25,7 +54,7 @@ fn show_screen_info() {
     print!(("\n  Background color: N/A"), fg: Color::White);
 }
 
-fn handle_set_command(_args: &[&str]) {
+fn handle_set_command(_args: &Args) {
     // TODO: Накорябал что-то не так в коде
     //       и теперь ОС ложится
     print!(("Doesn't work"), fg: Color::Yellow);
@@ -74,6 +103,35 @@ fn clear_screen() {
     print!(("\n"));
 }
 
+fn handle_font_command(args: &Args) -> Result<(), CommandError> {
+    args.expect_at_most(1)?;
+    let Some(name) = args.first() else {
+        print!(("\nCode page: "), fg: Color::LightBlue);
+        print!(("{}", vga_buffer::active_codepage().name), fg: Color::White);
+        print!(("\nAvailable fonts:"), fg: Color::LightBlue);
+        print!(("\n  bios   - ROM font (CP437)"), fg: Color::White);
+        for font in vga_buffer::FONTS.iter() {
+            print!(("\n  {:<6} - {} ({})", font.name, font.description, font.codepage.name), fg: Color::White);
+        }
+        return Ok(());
+    };
+
+    if name == "bios" {
+        if vga_buffer::restore_bios_font() {
+            print!(("\nROM font restored"), fg: Color::LightGreen);
+        } else {
+            print!(("\nROM font is already active"), fg: Color::Yellow);
+        }
+        return Ok(());
+    }
+
+    let font = vga_buffer::FONTS.iter().find(|font| font.name == name)
+        .ok_or(args.invalid(0, "unknown font, type 'screen font' for the list"))?;
+    vga_buffer::load_font(font);
+    print!(("\nFont {} loaded", font.name), fg: Color::LightGreen);
+    Ok(())
+}
+
 fn _parse_color(color: &str) -> Option<Color> {
     match color.to_lowercase().as_str() {
         "black" => Some(Color::Black),
@@ -141,12 +199,7 @@ fn print_available_colors() {
 }
 
 fn print_help() {
-    print!(("\nScreen commands:"), fg: Color::LightBlue);
-    print!(("\n  screen info                - Show screen status"), fg: Color::White);
-    print!(("\n  screen set fg <color>     - Set foreground color"), fg: Color::White);
-    print!(("\n  screen set bg <color>     - Set background color"), fg: Color::White);
-    print!(("\n  screen clear              - Clear the screen"), fg: Color::White);
-    print!(("\n  screen --help             - Show this help message"), fg: Color::White);
+    registry::print_help(&COMMAND);
     print!(("\n"));
     print_available_colors();
 }
@@ -0,0 +1,81 @@
+use crate::drivers::serial::{self, LineConfig, Parity};
+use crate::vga_buffer::Color;
+use crate::print;
+use super::registry::{arg, optional, sub, ArgKind, CommandSpec, Usage};
+use super::{Args, CommandError};
+
+pub static COMMAND: CommandSpec = CommandSpec {
+    name: "serial",
+    aliases: &[],
+    summary: "Serial port (COM1) settings",
+    usage: Usage {
+        subcommands: &[
+            sub("status", &[], "Show line settings and receive counters"),
+            sub(
+                "config",
+                &[
+                    arg("baud", ArgKind::Choice(&["9600", "19200", "38400", "57600", "115200"])),
+                    optional("format", ArgKind::Choice(&["8N1", "7E1", "7O1", "8N2"])),
+                ],
+                "Change baud rate and frame format",
+            ),
+        ],
+        args: &[],
+    },
+    run: run_serial_command,
+};
+
+fn run_serial_command(args: &Args) -> Result<(), CommandError> {
+    match args.first() {
+        None | Some("status") => {
+            args.rest().expect_at_most(0)?;
+            show_status();
+            Ok(())
+        }
+        Some("config") => handle_config_command(&args.rest()),
+        Some(_) => Err(args.unknown_subcommand()),
+    }
+}
+
+fn show_status() {
+    let (present, config) = {
+        let com1 = serial::COM1.lock();
+        (com1.is_present(), com1.config())
+    };
+    let stats = serial::stats();
+
+    print!(("\nCOM1 (0x{:03X}, IRQ {}):", serial::COM1_BASE, serial::IRQ), fg: Color::LightBlue);
+    if !present {
+        print!((" not detected"), fg: Color::Yellow);
+        return;
+    }
+    print!(("\n  Line:       {}", config), fg: Color::White);
+    print!(("\n  Receive:    {}", if stats.interrupts { "IRQ" } else { "off" }), fg: Color::White);
+    print!(("\n  Received:   {} bytes", stats.received), fg: Color::White);
+    print!(("\n  Dropped:    {} bytes", stats.dropped), fg: Color::White);
+    print!(("\n  Overruns:   {}", stats.overruns), fg: Color::White);
+}
+
+fn handle_config_command(args: &Args) -> Result<(), CommandError> {
+    args.expect_at_most(2)?;
+    let baud: u32 = args.number(0, "baud")?;
+    let mut config = LineConfig { baud, ..LineConfig::DEFAULT };
+
+    if let Some(format) = args.get(1) {
+        let &[data, parity, stop] = format.as_bytes() else {
+            return Err(args.invalid(1, "format is <data bits><N|E|O><stop bits>, e.g. 8N1"));
+        };
+        config.data_bits = data.wrapping_sub(b'0');
+        config.stop_bits = stop.wrapping_sub(b'0');
+        config.parity = match parity.to_ascii_uppercase() {
+            b'N' => Parity::None,
+            b'E' => Parity::Even,
+            b'O' => Parity::Odd,
+            _ => return Err(args.invalid(1, "parity must be N, E or O")),
+        };
+    }
+
+    serial::configure(config).map_err(CommandError::Failed)?;
+    print!(("\nCOM1 set to {}", config), fg: Color::LightGreen);
+    Ok(())
+}
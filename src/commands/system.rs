@@ -2,6 +2,31 @@ use x86_64::instructions::port::Port;
 use core::arch::asm;
 use crate::vga_buffer::Color;
 use crate::print;
+use super::registry::{CommandSpec, Usage};
+
+pub static REBOOT: CommandSpec = CommandSpec {
+    name: "reboot",
+    aliases: &["restart"],
+    summary: "Reboot the system",
+    usage: Usage { subcommands: &[], args: &[] },
+    run: |args| {
+        args.expect_at_most(0)?;
+        reboot();
+        Ok(())
+    },
+};
+
+pub static SHUTDOWN: CommandSpec = CommandSpec {
+    name: "shutdown",
+    aliases: &["poweroff"],
+    summary: "Shut down the system",
+    usage: Usage { subcommands: &[], args: &[] },
+    run: |args| {
+        args.expect_at_most(0)?;
+        shutdown();
+        Ok(())
+    },
+};
 
 pub fn reboot() {
     print!(("\nRebooting..."), fg: Color::Yellow);
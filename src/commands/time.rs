@@ -0,0 +1,47 @@
+use crate::drivers::{pit, tsc};
+use crate::vga_buffer::Color;
+use crate::print;
+use super::registry::{arg, ArgKind, CommandSpec, Usage};
+
+pub static UPTIME: CommandSpec = CommandSpec {
+    name: "uptime",
+    aliases: &[],
+    summary: "Show time since boot",
+    usage: Usage { subcommands: &[], args: &[] },
+    run: |args| {
+        args.expect_at_most(0)?;
+        show_uptime();
+        Ok(())
+    },
+};
+
+pub static SLEEP: CommandSpec = CommandSpec {
+    name: "sleep",
+    aliases: &[],
+    summary: "Wait for the given number of milliseconds",
+    usage: Usage { subcommands: &[], args: &[arg("ms", ArgKind::Number)] },
+    run: |args| {
+        args.expect_at_most(1)?;
+        let ms: u64 = args.number(0, "ms")?;
+        let start = tsc::Instant::now();
+        pit::sleep_ms(ms);
+        if tsc::frequency() != 0 {
+            let slept = start.elapsed();
+            print!(("\nSlept {}.{:03} ms", slept.as_millis(), slept.subsec_micros() % 1000), fg: Color::DarkGray);
+        }
+        Ok(())
+    },
+};
+
+fn show_uptime() {
+    let ms = pit::uptime_ms();
+    let seconds = ms / 1000;
+    let (hours, minutes) = (seconds / 3600, seconds / 60 % 60);
+
+    print!(("\nUp "), fg: Color::White);
+    if hours >= 24 {
+        print!(("{} days, ", hours / 24), fg: Color::LightGreen);
+    }
+    print!(("{}:{:02}:{:02}.{:03}", hours % 24, minutes, seconds % 60, ms % 1000), fg: Color::LightGreen);
+    print!((" ({} ticks at {} Hz)", pit::ticks(), pit::frequency()), fg: Color::DarkGray);
+}
@@ -0,0 +1,86 @@
+//! Куда идёт вывод `print!`.
+//!
+//! По умолчанию текст попадает и на экран, и на COM1 без оформления, чтобы
+//! с хоста было видно журнал загрузки. Пока шелл выполняет команду, вывод
+//! идёт только на консоль, с которой её ввели; на COM1 цвета передаются
+//! escape-последовательностями VT100.
+
+use core::fmt;
+use core::sync::atomic::{AtomicU8, Ordering};
+use crate::drivers::serial;
+use crate::vga_buffer::{self, Color};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Output {
+    All,
+    Screen,
+    Serial,
+}
+
+static OUTPUT: AtomicU8 = AtomicU8::new(Output::All as u8);
+
+pub fn output() -> Output {
+    match OUTPUT.load(Ordering::Relaxed) {
+        1 => Output::Screen,
+        2 => Output::Serial,
+        _ => Output::All,
+    }
+}
+
+/// Выполняет `f`, направив весь вывод в `output`.
+pub fn with_output<R>(output: Output, f: impl FnOnce() -> R) -> R {
+    let previous = OUTPUT.swap(output as u8, Ordering::Relaxed);
+    let result = f();
+    OUTPUT.store(previous, Ordering::Relaxed);
+    result
+}
+
+pub fn print(args: fmt::Arguments, fg: Option<Color>, bg: Option<Color>) {
+    match output() {
+        Output::All => {
+            vga_buffer::print(args, fg, bg);
+            serial::write_fmt(args);
+        }
+        Output::Screen => vga_buffer::print(args, fg, bg),
+        Output::Serial => print_serial(args, fg, bg),
+    }
+}
+
+fn print_serial(args: fmt::Arguments, fg: Option<Color>, bg: Option<Color>) {
+    if fg.is_none() && bg.is_none() {
+        serial::write_fmt(args);
+        return;
+    }
+
+    if let Some(color) = fg {
+        serial::write_fmt(format_args!("\x1b[{}m", ansi_color(color)));
+    }
+    if let Some(color) = bg {
+        serial::write_fmt(format_args!("\x1b[{}m", ansi_color(color) + 10));
+    }
+    serial::write_fmt(args);
+    serial::write_fmt(format_args!("\x1b[0m"));
+}
+
+/// Код цвета текста SGR; для фона к нему прибавляется 10.
+fn ansi_color(color: Color) -> u8 {
+    match color {
+        Color::Black => 30,
+        Color::Red => 31,
+        Color::Green => 32,
+        Color::Brown => 33,
+        Color::Blue => 34,
+        Color::Magenta => 35,
+        Color::Cyan => 36,
+        Color::LightGray => 37,
+        Color::DarkGray => 90,
+        Color::LightRed => 91,
+        Color::LightGreen => 92,
+        Color::Yellow => 93,
+        Color::LightBlue => 94,
+        Color::Pink => 95,
+        Color::LightCyan => 96,
+        Color::White => 97,
+    }
+}
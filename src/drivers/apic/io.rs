@@ -0,0 +1,112 @@
+//! I/O APIC: регистры читаются косвенно, номер пишется в IOREGSEL, значение
+//! берётся из IOWIN. Каждому входу соответствует 64-битная запись таблицы
+//! перенаправления: вектор, способ доставки и процессор-получатель.
+
+const IOREGSEL: u64 = 0x00;
+const IOWIN: u64 = 0x10;
+
+const REG_VERSION: u32 = 0x01;
+const REG_REDIRECTION: u32 = 0x10;
+
+/// Размер окна регистров.
+pub const SIZE: u64 = 0x20;
+
+pub const ENTRY_POLARITY_LOW: u64 = 1 << 13;
+pub const ENTRY_REMOTE_IRR: u64 = 1 << 14;
+pub const ENTRY_TRIGGER_LEVEL: u64 = 1 << 15;
+pub const ENTRY_MASKED: u64 = 1 << 16;
+const ENTRY_DESTINATION_SHIFT: u32 = 56;
+
+/// Запись таблицы перенаправления: фиксированная доставка, физический адрес
+/// получателя.
+pub fn entry(vector: u8, destination: u8, polarity_low: bool, level: bool) -> u64 {
+    let mut entry = vector as u64 | (destination as u64) << ENTRY_DESTINATION_SHIFT;
+    if polarity_low {
+        entry |= ENTRY_POLARITY_LOW;
+    }
+    if level {
+        entry |= ENTRY_TRIGGER_LEVEL;
+    }
+    entry
+}
+
+pub fn entry_vector(entry: u64) -> u8 {
+    entry as u8
+}
+
+pub fn entry_destination(entry: u64) -> u8 {
+    (entry >> ENTRY_DESTINATION_SHIFT) as u8
+}
+
+pub fn entry_delivery(entry: u64) -> &'static str {
+    match (entry >> 8) & 0x7 {
+        0 => "fixed",
+        1 => "lowest",
+        2 => "SMI",
+        4 => "NMI",
+        5 => "INIT",
+        7 => "ExtINT",
+        _ => "reserved",
+    }
+}
+
+#[derive(Debug)]
+pub struct IoApic {
+    base: u64,
+    pub gsi_base: u32,
+}
+
+impl IoApic {
+    /// # Safety
+    /// `base` — отображённое окно регистров I/O APIC.
+    pub const unsafe fn new(base: u64, gsi_base: u32) -> Self {
+        Self { base, gsi_base }
+    }
+
+    fn read(&mut self, reg: u32) -> u32 {
+        unsafe {
+            ((self.base + IOREGSEL) as *mut u32).write_volatile(reg);
+            ((self.base + IOWIN) as *const u32).read_volatile()
+        }
+    }
+
+    fn write(&mut self, reg: u32, value: u32) {
+        unsafe {
+            ((self.base + IOREGSEL) as *mut u32).write_volatile(reg);
+            ((self.base + IOWIN) as *mut u32).write_volatile(value);
+        }
+    }
+
+    pub fn version(&mut self) -> u8 {
+        self.read(REG_VERSION) as u8
+    }
+
+    /// Число входов, то есть записей в таблице перенаправления.
+    pub fn entries(&mut self) -> u32 {
+        ((self.read(REG_VERSION) >> 16) & 0xFF) + 1
+    }
+
+    pub fn redirection(&mut self, pin: u32) -> u64 {
+        let low = self.read(REG_REDIRECTION + pin * 2) as u64;
+        let high = self.read(REG_REDIRECTION + pin * 2 + 1) as u64;
+        low | high << 32
+    }
+
+    pub fn set_redirection(&mut self, pin: u32, entry: u64) {
+        // Сначала маскируем вход, чтобы не сработал с наполовину записанной
+        // записью, и снимаем маску последней записью
+        self.write(REG_REDIRECTION + pin * 2, ENTRY_MASKED as u32);
+        self.write(REG_REDIRECTION + pin * 2 + 1, (entry >> 32) as u32);
+        self.write(REG_REDIRECTION + pin * 2, entry as u32);
+    }
+
+    pub fn set_masked(&mut self, pin: u32, masked: bool) {
+        let low = self.read(REG_REDIRECTION + pin * 2);
+        let low = if masked {
+            low | ENTRY_MASKED as u32
+        } else {
+            low & !(ENTRY_MASKED as u32)
+        };
+        self.write(REG_REDIRECTION + pin * 2, low);
+    }
+}
@@ -0,0 +1,128 @@
+//! Local APIC: регистры по 32 бита, каждый на своих 16 байтах окна 4 КиБ.
+
+use x86_64::registers::model_specific::Msr;
+
+const IA32_APIC_BASE: u32 = 0x1B;
+const APIC_BASE_ENABLE: u64 = 1 << 11;
+
+const REG_ID: usize = 0x020;
+const REG_VERSION: usize = 0x030;
+const REG_TPR: usize = 0x080;
+const REG_EOI: usize = 0x0B0;
+const REG_SPURIOUS: usize = 0x0F0;
+const REG_LVT_TIMER: usize = 0x320;
+const REG_LVT_LINT0: usize = 0x350;
+const REG_TIMER_INITIAL: usize = 0x380;
+const REG_TIMER_CURRENT: usize = 0x390;
+const REG_TIMER_DIVIDE: usize = 0x3E0;
+
+const SPURIOUS_ENABLE: u32 = 1 << 8;
+pub const LVT_MASKED: u32 = 1 << 16;
+
+/// Размер окна регистров.
+pub const SIZE: u64 = 0x1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimerMode {
+    OneShot,
+    Periodic,
+    TscDeadline,
+    Reserved,
+}
+
+impl TimerMode {
+    pub fn name(self) -> &'static str {
+        match self {
+            TimerMode::OneShot => "one-shot",
+            TimerMode::Periodic => "periodic",
+            TimerMode::TscDeadline => "TSC deadline",
+            TimerMode::Reserved => "reserved",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Timer {
+    pub vector: u8,
+    pub masked: bool,
+    pub mode: TimerMode,
+    pub divide: u32,
+    pub initial: u32,
+    pub current: u32,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct LocalApic {
+    base: u64,
+}
+
+impl LocalApic {
+    /// # Safety
+    /// `base` — отображённое окно регистров LAPIC этого процессора.
+    pub const unsafe fn new(base: u64) -> Self {
+        Self { base }
+    }
+
+    fn read(&self, reg: usize) -> u32 {
+        unsafe { ((self.base as usize + reg) as *const u32).read_volatile() }
+    }
+
+    fn write(&self, reg: usize, value: u32) {
+        unsafe { ((self.base as usize + reg) as *mut u32).write_volatile(value) }
+    }
+
+    /// Включает LAPIC и направляет ложные прерывания на `spurious_vector`.
+    /// LINT0 маскируется: в режиме виртуального провода через него шли
+    /// прерывания 8259, теперь их доставляет I/O APIC.
+    pub fn enable(&self, spurious_vector: u8) {
+        unsafe {
+            let mut msr = Msr::new(IA32_APIC_BASE);
+            let value = msr.read();
+            msr.write(value | APIC_BASE_ENABLE);
+        }
+        self.write(REG_TPR, 0);
+        self.write(REG_LVT_LINT0, self.read(REG_LVT_LINT0) | LVT_MASKED);
+        self.write(REG_SPURIOUS, SPURIOUS_ENABLE | spurious_vector as u32);
+    }
+
+    pub fn end_of_interrupt(&self) {
+        self.write(REG_EOI, 0);
+    }
+
+    pub fn id(&self) -> u8 {
+        (self.read(REG_ID) >> 24) as u8
+    }
+
+    pub fn version(&self) -> u8 {
+        self.read(REG_VERSION) as u8
+    }
+
+    /// Число записей в таблице LVT.
+    pub fn max_lvt(&self) -> u8 {
+        ((self.read(REG_VERSION) >> 16) as u8).wrapping_add(1)
+    }
+
+    pub fn spurious(&self) -> u32 {
+        self.read(REG_SPURIOUS)
+    }
+
+    pub fn timer(&self) -> Timer {
+        let lvt = self.read(REG_LVT_TIMER);
+        let divide = self.read(REG_TIMER_DIVIDE);
+        // Биты 0, 1 и 3 задают делитель 2..128, 0b1011 — делитель 1
+        let shift = (divide & 0x3) | ((divide >> 1) & 0x4);
+        Timer {
+            vector: lvt as u8,
+            masked: lvt & LVT_MASKED != 0,
+            mode: match (lvt >> 17) & 0x3 {
+                0 => TimerMode::OneShot,
+                1 => TimerMode::Periodic,
+                2 => TimerMode::TscDeadline,
+                _ => TimerMode::Reserved,
+            },
+            divide: if shift == 7 { 1 } else { 2 << shift },
+            initial: self.read(REG_TIMER_INITIAL),
+            current: self.read(REG_TIMER_CURRENT),
+        }
+    }
+}
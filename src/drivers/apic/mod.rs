@@ -0,0 +1,230 @@
+//! Local APIC и I/O APIC.
+//!
+//! Контроллеры находятся по таблице MADT. Линии ISA маршрутизируются через
+//! I/O APIC на те же векторы, что были у 8259, так что обработчики в IDT не
+//! меняются; меняется только то, кому отправлять EOI. Пара 8259 после этого
+//! маскируется целиком. Без APIC или MADT ядро остаётся на 8259.
+
+pub mod io;
+pub mod local;
+
+use core::arch::x86_64::__cpuid;
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use x86_64::instructions::interrupts::without_interrupts;
+use x86_64::structures::idt::InterruptStackFrame;
+use x86_64::PhysAddr;
+use crate::acpi::madt::{Madt, Polarity, Trigger, MAX_IO_APICS};
+use crate::commands;
+use crate::drivers::pic;
+use crate::memory::paging;
+use crate::spin::SpinMutex;
+use io::IoApic;
+use local::LocalApic;
+
+/// Вектор ложных прерываний LAPIC. Младшие 4 бита на старых APIC обязаны
+/// быть единицами.
+pub const SPURIOUS_VECTOR: u8 = 0xFF;
+
+pub const LEGACY_IRQS: u8 = 16;
+// IRQ2 — каскад 8259, на I/O APIC его нет
+const CASCADE_IRQ: u8 = 2;
+
+/// Вход I/O APIC, к которому подключена линия ISA.
+#[derive(Debug, Clone, Copy)]
+pub struct Route {
+    pub io_apic: usize,
+    pub pin: u32,
+}
+
+struct Apics {
+    madt: Option<Madt>,
+    io: [Option<IoApic>; MAX_IO_APICS],
+    routes: [Option<Route>; LEGACY_IRQS as usize],
+}
+
+static APICS: SpinMutex<Apics> = SpinMutex::new(Apics {
+    madt: None,
+    io: [const { None }; MAX_IO_APICS],
+    routes: [None; LEGACY_IRQS as usize],
+});
+
+// EOI шлётся из обработчиков, поэтому адрес LAPIC лежит отдельно от замка
+static LOCAL_BASE: AtomicU64 = AtomicU64::new(0);
+static LOCAL_PHYS: AtomicU64 = AtomicU64::new(0);
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+static SPURIOUS: AtomicU64 = AtomicU64::new(0);
+
+/// Ложное прерывание: в ISR ничего не взведено, EOI не нужен.
+pub extern "x86-interrupt" fn spurious_handler(_frame: InterruptStackFrame) {
+    SPURIOUS.fetch_add(1, Ordering::Relaxed);
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Summary {
+    pub local_id: u8,
+    pub io_apics: usize,
+    pub routed: usize,
+}
+
+fn map(phys: u64, size: u64) -> Result<u64, &'static str> {
+    paging::map_mmio(PhysAddr::new(phys), size)
+        .map(|addr| addr.as_u64())
+        .map_err(|_| "cannot map APIC registers")
+}
+
+/// Переводит доставку IRQ с 8259 на I/O APIC. Линии, открытые на 8259,
+/// остаются открытыми. Вызывается при загрузке, после `pic::init`.
+pub fn init() -> Result<Summary, &'static str> {
+    if is_active() {
+        return Err("APIC is already active");
+    }
+    commands::register_all(&[&commands::apic::COMMAND]);
+    if __cpuid(1).edx & (1 << 9) == 0 {
+        return Err("CPU has no local APIC");
+    }
+    let madt = Madt::load()?;
+    if madt.io_apics.iter().all(Option::is_none) {
+        return Err("MADT lists no I/O APIC");
+    }
+
+    let local_base = map(madt.local_apic_address, local::SIZE)?;
+    let local = unsafe { LocalApic::new(local_base) };
+    let mut io_bases = [0; MAX_IO_APICS];
+    for (base, io) in io_bases.iter_mut().zip(madt.io_apics.iter()) {
+        if let Some(io) = io {
+            *base = map(io.address, io::SIZE)?;
+        }
+    }
+
+    without_interrupts(|| {
+        let (master_mask, slave_mask) = pic::get_masks();
+        let pic_masks = master_mask as u16 | (slave_mask as u16) << 8;
+
+        let mut apics = APICS.lock();
+        let apics = &mut *apics;
+        for ((slot, io), &base) in apics.io.iter_mut().zip(madt.io_apics.iter()).zip(io_bases.iter()) {
+            let Some(io) = io else { continue };
+            let mut io_apic = unsafe { IoApic::new(base, io.gsi_base) };
+            for pin in 0..io_apic.entries() {
+                io_apic.set_redirection(pin, io::ENTRY_MASKED);
+            }
+            *slot = Some(io_apic);
+        }
+
+        let destination = local.id();
+        let mut routed = 0;
+        for irq in (0..LEGACY_IRQS).filter(|&irq| irq != CASCADE_IRQ) {
+            let source = madt.route(irq);
+            let Some((index, pin)) = madt.io_apic_for(source.gsi) else { continue };
+            let Some(io_apic) = apics.io[index].as_mut() else { continue };
+            if pin >= io_apic.entries() {
+                continue;
+            }
+            let mut entry = io::entry(
+                pic::IRQ_OFFSET + irq,
+                destination,
+                source.polarity == Polarity::Low,
+                source.trigger == Trigger::Level,
+            );
+            if pic_masks & (1 << irq) != 0 {
+                entry |= io::ENTRY_MASKED;
+            }
+            io_apic.set_redirection(pin, entry);
+            apics.routes[irq as usize] = Some(Route { io_apic: index, pin });
+            routed += 1;
+        }
+        apics.madt = Some(madt);
+
+        pic::mask_all();
+        local.enable(SPURIOUS_VECTOR);
+        LOCAL_PHYS.store(madt.local_apic_address, Ordering::Relaxed);
+        LOCAL_BASE.store(local_base, Ordering::Relaxed);
+        ACTIVE.store(true, Ordering::Release);
+
+        Ok(Summary {
+            local_id: destination,
+            io_apics: madt.io_apics.iter().flatten().count(),
+            routed,
+        })
+    })
+}
+
+/// IRQ доставляет I/O APIC, а не 8259.
+pub fn is_active() -> bool {
+    ACTIVE.load(Ordering::Acquire)
+}
+
+/// LAPIC этого процессора, если он включён.
+pub fn local() -> Option<LocalApic> {
+    match LOCAL_BASE.load(Ordering::Relaxed) {
+        0 => None,
+        base => Some(unsafe { LocalApic::new(base) }),
+    }
+}
+
+pub fn local_phys() -> u64 {
+    LOCAL_PHYS.load(Ordering::Relaxed)
+}
+
+pub fn end_of_interrupt() {
+    if let Some(local) = local() {
+        local.end_of_interrupt();
+    }
+}
+
+/// Открывает или закрывает линию ISA `irq`. `false` — линия никуда не
+/// подключена.
+pub fn set_irq_masked(irq: u8, masked: bool) -> bool {
+    without_interrupts(|| {
+        let mut apics = APICS.lock();
+        let Some(route) = apics.routes.get(irq as usize).copied().flatten() else {
+            return false;
+        };
+        match apics.io[route.io_apic].as_mut() {
+            Some(io_apic) => {
+                io_apic.set_masked(route.pin, masked);
+                true
+            }
+            None => false,
+        }
+    })
+}
+
+pub fn spurious_count() -> u64 {
+    SPURIOUS.load(Ordering::Relaxed)
+}
+
+pub fn madt() -> Option<Madt> {
+    without_interrupts(|| APICS.lock().madt)
+}
+
+pub fn legacy_route(irq: u8) -> Option<Route> {
+    without_interrupts(|| APICS.lock().routes.get(irq as usize).copied().flatten())
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct IoApicInfo {
+    pub version: u8,
+    pub entries: u32,
+    pub gsi_base: u32,
+}
+
+pub fn io_apic_info(index: usize) -> Option<IoApicInfo> {
+    without_interrupts(|| {
+        let mut apics = APICS.lock();
+        let io_apic = apics.io.get_mut(index)?.as_mut()?;
+        Some(IoApicInfo {
+            version: io_apic.version(),
+            entries: io_apic.entries(),
+            gsi_base: io_apic.gsi_base,
+        })
+    })
+}
+
+pub fn redirection(index: usize, pin: u32) -> Option<u64> {
+    without_interrupts(|| {
+        let mut apics = APICS.lock();
+        let io_apic = apics.io.get_mut(index)?.as_mut()?;
+        (pin < io_apic.entries()).then(|| io_apic.redirection(pin))
+    })
+}
@@ -1,3 +1,4 @@
+use crate::drivers::pit;
 use crate::port::{inb, outb};
 use core::fmt;
 
@@ -66,7 +67,8 @@ impl AtaController {
             
             outb(self.base + 7, 0xEC);
             
-            for _ in 0..1000 { core::arch::asm!("pause"); }
+            // Статус готов не раньше чем через 400 нс после команды
+            pit::busy_wait_us(1);
             
             let status = inb(self.base + 7);
             if status == 0 || (status & 0x80) != 0 {
@@ -136,7 +138,7 @@ impl AtaController {
                 return Err(AtaError::DeviceFault(status));
             }
             
-            for _ in 0..1000 { unsafe { core::arch::asm!("pause"); } }
+            pit::busy_wait_us(10);
         }
         Err(AtaError::Timeout)
     }
@@ -157,7 +159,7 @@ impl AtaController {
                 return Ok(());
             }
             
-            for _ in 0..1000 { unsafe { core::arch::asm!("pause"); } }
+            pit::busy_wait_us(10);
         }
         Err(AtaError::Timeout)
     }
@@ -0,0 +1,35 @@
+//! Линии IRQ независимо от того, кто их доставляет: пара 8259 или I/O APIC.
+//! Драйверы открывают свои линии и шлют EOI только через этот модуль.
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+use crate::drivers::{apic, pic};
+
+// Сколько обработчиков IRQ сейчас на стеке: от `enter` до EOI
+static DEPTH: AtomicUsize = AtomicUsize::new(0);
+
+/// Первое, что делает обработчик IRQ. Парный вызов — `end_of_interrupt`.
+pub fn enter() {
+    DEPTH.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Выполняется обработчик IRQ, который ещё не отправил EOI.
+pub fn in_handler() -> bool {
+    DEPTH.load(Ordering::Relaxed) != 0
+}
+
+pub fn unmask(irq: u8) {
+    if apic::is_active() {
+        apic::set_irq_masked(irq, false);
+    } else {
+        pic::unmask_irq(irq);
+    }
+}
+
+pub fn end_of_interrupt(irq: u8) {
+    DEPTH.fetch_sub(1, Ordering::Relaxed);
+    if apic::is_active() {
+        apic::end_of_interrupt();
+    } else {
+        pic::send_eoi(irq);
+    }
+}
@@ -0,0 +1,323 @@
+/// Физическая клавиша. Значение совпадает с make-кодом set 1,
+/// у клавиш с префиксом 0xE0 дополнительно выставлен старший бит.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum KeyCode {
+    Escape = 0x01,
+    Key1 = 0x02,
+    Key2 = 0x03,
+    Key3 = 0x04,
+    Key4 = 0x05,
+    Key5 = 0x06,
+    Key6 = 0x07,
+    Key7 = 0x08,
+    Key8 = 0x09,
+    Key9 = 0x0A,
+    Key0 = 0x0B,
+    Minus = 0x0C,
+    Equals = 0x0D,
+    Backspace = 0x0E,
+    Tab = 0x0F,
+    Q = 0x10,
+    W = 0x11,
+    E = 0x12,
+    R = 0x13,
+    T = 0x14,
+    Y = 0x15,
+    U = 0x16,
+    I = 0x17,
+    O = 0x18,
+    P = 0x19,
+    LeftBracket = 0x1A,
+    RightBracket = 0x1B,
+    Enter = 0x1C,
+    LeftCtrl = 0x1D,
+    A = 0x1E,
+    S = 0x1F,
+    D = 0x20,
+    F = 0x21,
+    G = 0x22,
+    H = 0x23,
+    J = 0x24,
+    K = 0x25,
+    L = 0x26,
+    Semicolon = 0x27,
+    Quote = 0x28,
+    Backtick = 0x29,
+    LeftShift = 0x2A,
+    Backslash = 0x2B,
+    Z = 0x2C,
+    X = 0x2D,
+    C = 0x2E,
+    V = 0x2F,
+    B = 0x30,
+    N = 0x31,
+    M = 0x32,
+    Comma = 0x33,
+    Period = 0x34,
+    Slash = 0x35,
+    RightShift = 0x36,
+    KeypadMultiply = 0x37,
+    LeftAlt = 0x38,
+    Space = 0x39,
+    CapsLock = 0x3A,
+    F1 = 0x3B,
+    F2 = 0x3C,
+    F3 = 0x3D,
+    F4 = 0x3E,
+    F5 = 0x3F,
+    F6 = 0x40,
+    F7 = 0x41,
+    F8 = 0x42,
+    F9 = 0x43,
+    F10 = 0x44,
+    NumLock = 0x45,
+    ScrollLock = 0x46,
+    Keypad7 = 0x47,
+    Keypad8 = 0x48,
+    Keypad9 = 0x49,
+    KeypadMinus = 0x4A,
+    Keypad4 = 0x4B,
+    Keypad5 = 0x4C,
+    Keypad6 = 0x4D,
+    KeypadPlus = 0x4E,
+    Keypad1 = 0x4F,
+    Keypad2 = 0x50,
+    Keypad3 = 0x51,
+    Keypad0 = 0x52,
+    KeypadPeriod = 0x53,
+    NonUsBackslash = 0x56,
+    F11 = 0x57,
+    F12 = 0x58,
+
+    KeypadEnter = 0x9C,
+    RightCtrl = 0x9D,
+    KeypadDivide = 0xB5,
+    PrintScreen = 0xB7,
+    RightAlt = 0xB8,
+    Pause = 0xC5,
+    Home = 0xC7,
+    ArrowUp = 0xC8,
+    PageUp = 0xC9,
+    ArrowLeft = 0xCB,
+    ArrowRight = 0xCD,
+    End = 0xCF,
+    ArrowDown = 0xD0,
+    PageDown = 0xD1,
+    Insert = 0xD2,
+    Delete = 0xD3,
+    LeftGui = 0xDB,
+    RightGui = 0xDC,
+    Menu = 0xDD,
+}
+
+impl KeyCode {
+    pub fn from_scancode(code: u8, extended: bool) -> Option<KeyCode> {
+        use KeyCode::*;
+
+        if extended {
+            return match code {
+                0x1C => Some(KeypadEnter),
+                0x1D => Some(RightCtrl),
+                0x35 => Some(KeypadDivide),
+                0x37 => Some(PrintScreen),
+                0x38 => Some(RightAlt),
+                // Ctrl+Pause приходит как E0 46
+                0x46 => Some(Pause),
+                0x47 => Some(Home),
+                0x48 => Some(ArrowUp),
+                0x49 => Some(PageUp),
+                0x4B => Some(ArrowLeft),
+                0x4D => Some(ArrowRight),
+                0x4F => Some(End),
+                0x50 => Some(ArrowDown),
+                0x51 => Some(PageDown),
+                0x52 => Some(Insert),
+                0x53 => Some(Delete),
+                0x5B => Some(LeftGui),
+                0x5C => Some(RightGui),
+                0x5D => Some(Menu),
+                _ => None,
+            };
+        }
+
+        let key = match code {
+            0x01 => Escape,
+            0x02 => Key1,
+            0x03 => Key2,
+            0x04 => Key3,
+            0x05 => Key4,
+            0x06 => Key5,
+            0x07 => Key6,
+            0x08 => Key7,
+            0x09 => Key8,
+            0x0A => Key9,
+            0x0B => Key0,
+            0x0C => Minus,
+            0x0D => Equals,
+            0x0E => Backspace,
+            0x0F => Tab,
+            0x10 => Q,
+            0x11 => W,
+            0x12 => E,
+            0x13 => R,
+            0x14 => T,
+            0x15 => Y,
+            0x16 => U,
+            0x17 => I,
+            0x18 => O,
+            0x19 => P,
+            0x1A => LeftBracket,
+            0x1B => RightBracket,
+            0x1C => Enter,
+            0x1D => LeftCtrl,
+            0x1E => A,
+            0x1F => S,
+            0x20 => D,
+            0x21 => F,
+            0x22 => G,
+            0x23 => H,
+            0x24 => J,
+            0x25 => K,
+            0x26 => L,
+            0x27 => Semicolon,
+            0x28 => Quote,
+            0x29 => Backtick,
+            0x2A => LeftShift,
+            0x2B => Backslash,
+            0x2C => Z,
+            0x2D => X,
+            0x2E => C,
+            0x2F => V,
+            0x30 => B,
+            0x31 => N,
+            0x32 => M,
+            0x33 => Comma,
+            0x34 => Period,
+            0x35 => Slash,
+            0x36 => RightShift,
+            0x37 => KeypadMultiply,
+            0x38 => LeftAlt,
+            0x39 => Space,
+            0x3A => CapsLock,
+            0x3B => F1,
+            0x3C => F2,
+            0x3D => F3,
+            0x3E => F4,
+            0x3F => F5,
+            0x40 => F6,
+            0x41 => F7,
+            0x42 => F8,
+            0x43 => F9,
+            0x44 => F10,
+            0x45 => NumLock,
+            0x46 => ScrollLock,
+            0x47 => Keypad7,
+            0x48 => Keypad8,
+            0x49 => Keypad9,
+            0x4A => KeypadMinus,
+            0x4B => Keypad4,
+            0x4C => Keypad5,
+            0x4D => Keypad6,
+            0x4E => KeypadPlus,
+            0x4F => Keypad1,
+            0x50 => Keypad2,
+            0x51 => Keypad3,
+            0x52 => Keypad0,
+            0x53 => KeypadPeriod,
+            0x56 => NonUsBackslash,
+            0x57 => F11,
+            0x58 => F12,
+            _ => return None,
+        };
+        Some(key)
+    }
+
+    pub fn is_extended(self) -> bool {
+        self as u8 & 0x80 != 0
+    }
+
+    /// Клавиши цифрового блока, которые при выключенном NumLock работают как навигация.
+    pub fn keypad_navigation(self) -> Option<KeyCode> {
+        use KeyCode::*;
+        match self {
+            Keypad7 => Some(Home),
+            Keypad8 => Some(ArrowUp),
+            Keypad9 => Some(PageUp),
+            Keypad4 => Some(ArrowLeft),
+            Keypad6 => Some(ArrowRight),
+            Keypad1 => Some(End),
+            Keypad2 => Some(ArrowDown),
+            Keypad3 => Some(PageDown),
+            Keypad0 => Some(Insert),
+            KeypadPeriod => Some(Delete),
+            _ => None,
+        }
+    }
+
+    pub fn keypad_char(self) -> Option<char> {
+        use KeyCode::*;
+        match self {
+            Keypad0 => Some('0'),
+            Keypad1 => Some('1'),
+            Keypad2 => Some('2'),
+            Keypad3 => Some('3'),
+            Keypad4 => Some('4'),
+            Keypad5 => Some('5'),
+            Keypad6 => Some('6'),
+            Keypad7 => Some('7'),
+            Keypad8 => Some('8'),
+            Keypad9 => Some('9'),
+            KeypadPeriod => Some('.'),
+            KeypadPlus => Some('+'),
+            KeypadMinus => Some('-'),
+            KeypadMultiply => Some('*'),
+            KeypadDivide => Some('/'),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Modifiers {
+    pub left_shift: bool,
+    pub right_shift: bool,
+    pub left_ctrl: bool,
+    pub right_ctrl: bool,
+    pub left_alt: bool,
+    pub right_alt: bool,
+    pub caps_lock: bool,
+    pub num_lock: bool,
+    pub scroll_lock: bool,
+}
+
+impl Modifiers {
+    pub const fn new() -> Self {
+        Self {
+            left_shift: false,
+            right_shift: false,
+            left_ctrl: false,
+            right_ctrl: false,
+            left_alt: false,
+            right_alt: false,
+            caps_lock: false,
+            num_lock: false,
+            scroll_lock: false,
+        }
+    }
+
+    pub fn shift(&self) -> bool {
+        self.left_shift || self.right_shift
+    }
+
+    pub fn ctrl(&self) -> bool {
+        self.left_ctrl || self.right_ctrl
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyEvent {
+    pub key: KeyCode,
+    pub modifiers: Modifiers,
+    pub pressed: bool,
+}
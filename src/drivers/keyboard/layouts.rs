@@ -0,0 +1,214 @@
+use core::sync::atomic::{AtomicUsize, Ordering};
+use super::KeyEvent;
+
+pub struct Layout {
+    pub name: &'static str,
+    pub description: &'static str,
+    normal: [char; 128],
+    shift: [char; 128],
+    altgr: [char; 128],
+}
+
+impl Layout {
+    pub fn translate(&self, event: KeyEvent) -> Option<char> {
+        if !event.pressed {
+            return None;
+        }
+
+        if let Some(character) = event.key.keypad_char() {
+            return Some(character);
+        }
+
+        if event.key.is_extended() {
+            return None;
+        }
+
+        let code = event.key as usize;
+        let modifiers = event.modifiers;
+
+        // AltGr приходит как правый Alt; Ctrl+Alt по традиции тоже считается AltGr
+        if modifiers.right_alt || (modifiers.ctrl() && modifiers.left_alt) {
+            return Some(self.altgr[code]).filter(|&c| c != '\0');
+        }
+
+        if modifiers.ctrl() || modifiers.left_alt {
+            return None;
+        }
+
+        let normal = self.normal[code];
+        let shifted = self.shift[code];
+
+        // CapsLock инвертирует Shift только для букв, у которых есть заглавная пара
+        let letter = normal.is_alphabetic() && shifted.is_alphabetic();
+        let character = if modifiers.shift() != (modifiers.caps_lock && letter) {
+            shifted
+        } else {
+            normal
+        };
+
+        Some(character).filter(|&c| c != '\0')
+    }
+}
+
+pub static US: Layout = Layout {
+    name: "us",
+    description: "US QWERTY",
+    normal: [
+        '\0', '\0', '1', '2', '3', '4', '5', '6',
+        '7', '8', '9', '0', '-', '=', '\0', '\0',
+        'q', 'w', 'e', 'r', 't', 'y', 'u', 'i',
+        'o', 'p', '[', ']', '\0', '\0', 'a', 's',
+        'd', 'f', 'g', 'h', 'j', 'k', 'l', ';',
+        '\'', '`', '\0', '\\', 'z', 'x', 'c', 'v',
+        'b', 'n', 'm', ',', '.', '/', '\0', '\0',
+        '\0', ' ', '\0', '\0', '\0', '\0', '\0', '\0',
+        '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0',
+        '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0',
+        '\0', '\0', '\0', '\0', '\0', '\0', '\\', '\0',
+        '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0',
+        '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0',
+        '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0',
+        '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0',
+        '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0',
+    ],
+    shift: [
+        '\0', '\0', '!', '@', '#', '$', '%', '^',
+        '&', '*', '(', ')', '_', '+', '\0', '\0',
+        'Q', 'W', 'E', 'R', 'T', 'Y', 'U', 'I',
+        'O', 'P', '{', '}', '\0', '\0', 'A', 'S',
+        'D', 'F', 'G', 'H', 'J', 'K', 'L', ':',
+        '"', '~', '\0', '|', 'Z', 'X', 'C', 'V',
+        'B', 'N', 'M', '<', '>', '?', '\0', '\0',
+        '\0', ' ', '\0', '\0', '\0', '\0', '\0', '\0',
+        '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0',
+        '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0',
+        '\0', '\0', '\0', '\0', '\0', '\0', '|', '\0',
+        '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0',
+        '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0',
+        '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0',
+        '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0',
+        '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0',
+    ],
+    altgr: ['\0'; 128],
+};
+
+pub static RUSSIAN: Layout = Layout {
+    name: "ru",
+    description: "Русская ЙЦУКЕН",
+    normal: [
+        '\0', '\0', '1', '2', '3', '4', '5', '6',
+        '7', '8', '9', '0', '-', '=', '\0', '\0',
+        'й', 'ц', 'у', 'к', 'е', 'н', 'г', 'ш',
+        'щ', 'з', 'х', 'ъ', '\0', '\0', 'ф', 'ы',
+        'в', 'а', 'п', 'р', 'о', 'л', 'д', 'ж',
+        'э', 'ё', '\0', '\\', 'я', 'ч', 'с', 'м',
+        'и', 'т', 'ь', 'б', 'ю', '.', '\0', '\0',
+        '\0', ' ', '\0', '\0', '\0', '\0', '\0', '\0',
+        '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0',
+        '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0',
+        '\0', '\0', '\0', '\0', '\0', '\0', '\\', '\0',
+        '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0',
+        '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0',
+        '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0',
+        '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0',
+        '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0',
+    ],
+    shift: [
+        '\0', '\0', '!', '"', '№', ';', '%', ':',
+        '?', '*', '(', ')', '_', '+', '\0', '\0',
+        'Й', 'Ц', 'У', 'К', 'Е', 'Н', 'Г', 'Ш',
+        'Щ', 'З', 'Х', 'Ъ', '\0', '\0', 'Ф', 'Ы',
+        'В', 'А', 'П', 'Р', 'О', 'Л', 'Д', 'Ж',
+        'Э', 'Ё', '\0', '/', 'Я', 'Ч', 'С', 'М',
+        'И', 'Т', 'Ь', 'Б', 'Ю', ',', '\0', '\0',
+        '\0', ' ', '\0', '\0', '\0', '\0', '\0', '\0',
+        '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0',
+        '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0',
+        '\0', '\0', '\0', '\0', '\0', '\0', '/', '\0',
+        '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0',
+        '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0',
+        '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0',
+        '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0',
+        '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0',
+    ],
+    altgr: ['\0'; 128],
+};
+
+pub static GERMAN: Layout = Layout {
+    name: "de",
+    description: "Deutsch QWERTZ",
+    normal: [
+        '\0', '\0', '1', '2', '3', '4', '5', '6',
+        '7', '8', '9', '0', 'ß', '´', '\0', '\0',
+        'q', 'w', 'e', 'r', 't', 'z', 'u', 'i',
+        'o', 'p', 'ü', '+', '\0', '\0', 'a', 's',
+        'd', 'f', 'g', 'h', 'j', 'k', 'l', 'ö',
+        'ä', '^', '\0', '#', 'y', 'x', 'c', 'v',
+        'b', 'n', 'm', ',', '.', '-', '\0', '\0',
+        '\0', ' ', '\0', '\0', '\0', '\0', '\0', '\0',
+        '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0',
+        '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0',
+        '\0', '\0', '\0', '\0', '\0', '\0', '<', '\0',
+        '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0',
+        '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0',
+        '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0',
+        '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0',
+        '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0',
+    ],
+    shift: [
+        '\0', '\0', '!', '"', '§', '$', '%', '&',
+        '/', '(', ')', '=', '?', '`', '\0', '\0',
+        'Q', 'W', 'E', 'R', 'T', 'Z', 'U', 'I',
+        'O', 'P', 'Ü', '*', '\0', '\0', 'A', 'S',
+        'D', 'F', 'G', 'H', 'J', 'K', 'L', 'Ö',
+        'Ä', '°', '\0', '\'', 'Y', 'X', 'C', 'V',
+        'B', 'N', 'M', ';', ':', '_', '\0', '\0',
+        '\0', ' ', '\0', '\0', '\0', '\0', '\0', '\0',
+        '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0',
+        '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0',
+        '\0', '\0', '\0', '\0', '\0', '\0', '>', '\0',
+        '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0',
+        '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0',
+        '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0',
+        '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0',
+        '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0',
+    ],
+    altgr: [
+        '\0', '\0', '\0', '²', '³', '\0', '\0', '\0',
+        '{', '[', ']', '}', '\\', '\0', '\0', '\0',
+        '@', '\0', '€', '\0', '\0', '\0', '\0', '\0',
+        '\0', '\0', '\0', '~', '\0', '\0', '\0', '\0',
+        '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0',
+        '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0',
+        '\0', '\0', 'µ', '\0', '\0', '\0', '\0', '\0',
+        '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0',
+        '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0',
+        '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0',
+        '\0', '\0', '\0', '\0', '\0', '\0', '|', '\0',
+        '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0',
+        '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0',
+        '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0',
+        '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0',
+        '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0',
+    ],
+};
+
+pub static LAYOUTS: [&Layout; 3] = [&US, &RUSSIAN, &GERMAN];
+
+static ACTIVE: AtomicUsize = AtomicUsize::new(0);
+
+pub fn active() -> &'static Layout {
+    LAYOUTS[ACTIVE.load(Ordering::Relaxed)]
+}
+
+pub fn select(name: &str) -> Option<&'static Layout> {
+    let index = LAYOUTS.iter().position(|layout| layout.name == name)?;
+    ACTIVE.store(index, Ordering::Relaxed);
+    Some(LAYOUTS[index])
+}
+
+pub fn cycle() -> &'static Layout {
+    let index = (ACTIVE.load(Ordering::Relaxed) + 1) % LAYOUTS.len();
+    ACTIVE.store(index, Ordering::Relaxed);
+    LAYOUTS[index]
+}
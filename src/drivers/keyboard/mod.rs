@@ -0,0 +1,191 @@
+use x86_64::instructions::port::Port;
+use x86_64::structures::idt::InterruptStackFrame;
+use crate::commands;
+use crate::drivers::irq;
+use crate::drivers::queue::ByteQueue;
+use crate::spin::SpinMutex;
+
+mod keys;
+pub mod layouts;
+
+pub use keys::{KeyCode, KeyEvent, Modifiers};
+
+pub const IRQ: u8 = 1;
+
+const DATA_PORT: u16 = 0x60;
+const STATUS_PORT: u16 = 0x64;
+const STATUS_OUTPUT_FULL: u8 = 0x01;
+
+const QUEUE_SIZE: usize = 128;
+
+const BREAK_BIT: u8 = 0x80;
+const EXTENDED_PREFIX: u8 = 0xE0;
+const PAUSE_PREFIX: u8 = 0xE1;
+// E1 1D 45 E1 9D C5: после префикса ещё пять байт, break-кода у Pause нет
+const PAUSE_SEQUENCE_LEN: u8 = 5;
+// Фальшивые Shift'ы, которыми клавиатура обрамляет PrintScreen и навигацию при NumLock
+const FAKE_LEFT_SHIFT: u8 = 0x2A;
+const FAKE_RIGHT_SHIFT: u8 = 0x36;
+
+// Флаги клавиатуры в BIOS Data Area, там BIOS хранит состояние индикаторов
+const BDA_KEYBOARD_FLAGS: usize = 0x417;
+const BDA_SCROLL_LOCK: u8 = 1 << 4;
+const BDA_NUM_LOCK: u8 = 1 << 5;
+const BDA_CAPS_LOCK: u8 = 1 << 6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DecoderState {
+    Start,
+    Extended,
+    Pause(u8),
+}
+
+pub struct Keyboard {
+    state: DecoderState,
+    modifiers: Modifiers,
+}
+
+impl Keyboard {
+    pub const fn new() -> Self {
+        Self {
+            state: DecoderState::Start,
+            modifiers: Modifiers::new(),
+        }
+    }
+
+    pub fn process(&mut self, scancode: u8) -> Option<KeyEvent> {
+        match self.state {
+            DecoderState::Pause(remaining) => {
+                if remaining > 1 {
+                    self.state = DecoderState::Pause(remaining - 1);
+                    return None;
+                }
+                self.state = DecoderState::Start;
+                return Some(self.event(KeyCode::Pause, true));
+            }
+            DecoderState::Start => match scancode {
+                EXTENDED_PREFIX => {
+                    self.state = DecoderState::Extended;
+                    return None;
+                }
+                PAUSE_PREFIX => {
+                    self.state = DecoderState::Pause(PAUSE_SEQUENCE_LEN);
+                    return None;
+                }
+                _ => {}
+            },
+            DecoderState::Extended => {}
+        }
+
+        let extended = self.state == DecoderState::Extended;
+        self.state = DecoderState::Start;
+
+        let code = scancode & !BREAK_BIT;
+        let pressed = scancode & BREAK_BIT == 0;
+
+        if extended && (code == FAKE_LEFT_SHIFT || code == FAKE_RIGHT_SHIFT) {
+            return None;
+        }
+
+        let mut key = KeyCode::from_scancode(code, extended)?;
+        self.update_modifiers(key, pressed);
+
+        if pressed && self.is_layout_hotkey(key) {
+            layouts::cycle();
+        }
+
+        if !self.modifiers.num_lock {
+            if let Some(navigation) = key.keypad_navigation() {
+                key = navigation;
+            }
+        }
+
+        Some(self.event(key, pressed))
+    }
+
+    fn update_modifiers(&mut self, key: KeyCode, pressed: bool) {
+        let modifiers = &mut self.modifiers;
+        match key {
+            KeyCode::LeftShift => modifiers.left_shift = pressed,
+            KeyCode::RightShift => modifiers.right_shift = pressed,
+            KeyCode::LeftCtrl => modifiers.left_ctrl = pressed,
+            KeyCode::RightCtrl => modifiers.right_ctrl = pressed,
+            KeyCode::LeftAlt => modifiers.left_alt = pressed,
+            KeyCode::RightAlt => modifiers.right_alt = pressed,
+            // Автоповтор присылает make без break, поэтому переключаем только
+            // на отпускании, а не на каждом make
+            KeyCode::CapsLock if !pressed => modifiers.caps_lock = !modifiers.caps_lock,
+            KeyCode::NumLock if !pressed => modifiers.num_lock = !modifiers.num_lock,
+            KeyCode::ScrollLock if !pressed => modifiers.scroll_lock = !modifiers.scroll_lock,
+            _ => {}
+        }
+    }
+
+    fn is_layout_hotkey(&self, key: KeyCode) -> bool {
+        match key {
+            KeyCode::LeftShift | KeyCode::RightShift => self.modifiers.left_alt,
+            KeyCode::LeftAlt => self.modifiers.shift(),
+            _ => false,
+        }
+    }
+
+    fn event(&self, key: KeyCode, pressed: bool) -> KeyEvent {
+        KeyEvent {
+            key,
+            modifiers: self.modifiers,
+            pressed,
+        }
+    }
+}
+
+impl KeyEvent {
+    /// Символ по активной раскладке.
+    pub fn to_char(self) -> Option<char> {
+        layouts::active().translate(self)
+    }
+}
+
+static QUEUE: ByteQueue<QUEUE_SIZE> = ByteQueue::new();
+
+pub static KEYBOARD: SpinMutex<Keyboard> = SpinMutex::new(Keyboard::new());
+
+pub extern "x86-interrupt" fn interrupt_handler(_frame: InterruptStackFrame) {
+    irq::enter();
+    let scancode = unsafe { Port::<u8>::new(DATA_PORT).read() };
+    // Если основной цикл не успевает, лишние нажатия просто теряются
+    let _ = QUEUE.push(scancode);
+    irq::end_of_interrupt(IRQ);
+}
+
+pub fn init() {
+    commands::register_all(&[&commands::kbd::COMMAND]);
+    let flags = unsafe { (BDA_KEYBOARD_FLAGS as *const u8).read_volatile() };
+    {
+        let mut keyboard = KEYBOARD.lock();
+        keyboard.modifiers.caps_lock = flags & BDA_CAPS_LOCK != 0;
+        keyboard.modifiers.num_lock = flags & BDA_NUM_LOCK != 0;
+        keyboard.modifiers.scroll_lock = flags & BDA_SCROLL_LOCK != 0;
+    }
+
+    unsafe {
+        let mut status = Port::<u8>::new(STATUS_PORT);
+        let mut data = Port::<u8>::new(DATA_PORT);
+        while status.read() & STATUS_OUTPUT_FULL != 0 {
+            data.read();
+        }
+    }
+    irq::unmask(IRQ);
+}
+
+pub fn has_input() -> bool {
+    !QUEUE.is_empty()
+}
+
+pub fn read_event() -> Option<KeyEvent> {
+    while let Some(scancode) = QUEUE.pop() {
+        if let Some(event) = KEYBOARD.lock().process(scancode) {
+            return Some(event);
+        }
+    }
+    None
+}
@@ -1,2 +1,10 @@
+pub mod apic;
 pub mod pic;
 pub mod ata;
+pub mod irq;
+pub mod keyboard;
+pub mod pit;
+pub mod queue;
+pub mod rtc;
+pub mod serial;
+pub mod tsc;
@@ -1,5 +1,9 @@
+use crate::commands;
+use crate::drivers::irq;
 use crate::spin::SpinMutex;
+use x86_64::instructions::interrupts::without_interrupts;
 use x86_64::instructions::port::Port;
+use x86_64::structures::idt::InterruptStackFrame;
 
 const PIC1_CMD: u16 = 0x20;
 const PIC1_DATA: u16 = 0x21;
@@ -13,7 +17,15 @@ const ICW4_8086: u8 = 0x01;
 
 const PIC_EOI: u8 = 0x20;
 
-const IRQ_OFFSET: u8 = 0x20;
+// OCW3: какой регистр вернёт следующее чтение порта команд
+const OCW3_READ_IRR: u8 = 0x0A;
+const OCW3_READ_ISR: u8 = 0x0B;
+
+// IRQ7 и IRQ15 — последние линии пары, на них 8259 выдаёт ложные прерывания
+pub const MASTER_SPURIOUS_IRQ: u8 = 7;
+pub const SLAVE_SPURIOUS_IRQ: u8 = 15;
+
+pub const IRQ_OFFSET: u8 = 0x20;
 
 pub struct Pic {
     offset: u8,
@@ -34,6 +46,22 @@ impl Pic {
         unsafe { self.command.write(PIC_EOI); }
     }
 
+    /// Запросы, которые обслуживаются сейчас (In-Service Register).
+    pub fn read_isr(&mut self) -> u8 {
+        unsafe {
+            self.command.write(OCW3_READ_ISR);
+            self.command.read()
+        }
+    }
+
+    /// Запросы, которые ждут обслуживания (Interrupt Request Register).
+    pub fn read_irr(&mut self) -> u8 {
+        unsafe {
+            self.command.write(OCW3_READ_IRR);
+            self.command.read()
+        }
+    }
+
     pub fn read_mask(&mut self) -> u8 {
         unsafe { self.data.read() }
     }
@@ -46,6 +74,8 @@ impl Pic {
 pub struct ChainedPics {
     pub master: Pic,
     pub slave: Pic,
+    /// Ложные IRQ7 и IRQ15.
+    pub spurious: (u64, u64),
 }
 
 impl ChainedPics {
@@ -53,6 +83,7 @@ impl ChainedPics {
         Self {
             master: Pic::new(IRQ_OFFSET, PIC1_CMD, PIC1_DATA),
             slave: Pic::new(IRQ_OFFSET + 8, PIC2_CMD, PIC2_DATA),
+            spurious: (0, 0),
         }
     }
 
@@ -92,47 +123,135 @@ impl ChainedPics {
         master_ok && slave_ok
     }
 
-    pub fn notify_end_of_interrupt(&mut self, irq: u8) {
+    /// Шлёт EOI, если прерывание настоящее. Ложное IRQ7 или IRQ15 8259
+    /// выдаёт, когда запрос пропал до подтверждения: бит в ISR тогда не
+    /// взведён, и EOI сбросил бы чужой запрос. `false` — прерывание ложное.
+    pub fn notify_end_of_interrupt(&mut self, irq: u8) -> bool {
+        if irq == MASTER_SPURIOUS_IRQ && self.master.read_isr() & (1 << 7) == 0 {
+            self.spurious.0 += 1;
+            return false;
+        }
+        if irq == SLAVE_SPURIOUS_IRQ && self.slave.read_isr() & (1 << 7) == 0 {
+            self.spurious.1 += 1;
+            // Ведущий же видел настоящий запрос по каскаду, ему EOI нужен
+            self.master.end_of_interrupt();
+            return false;
+        }
+
         if irq >= 8 {
             self.slave.end_of_interrupt();
         }
         self.master.end_of_interrupt();
+        true
     }
 }
 
 pub static PICS: SpinMutex<ChainedPics> = SpinMutex::new(ChainedPics::new());
 
 pub fn get_masks() -> (u8, u8) {
-    let mut pics = PICS.lock();
-    (pics.master.read_mask(), pics.slave.read_mask())
+    without_interrupts(|| {
+        let mut pics = PICS.lock();
+        (pics.master.read_mask(), pics.slave.read_mask())
+    })
+}
+
+/// ISR ведущего и ведомого.
+pub fn get_isr() -> (u8, u8) {
+    without_interrupts(|| {
+        let mut pics = PICS.lock();
+        (pics.master.read_isr(), pics.slave.read_isr())
+    })
+}
+
+/// IRR ведущего и ведомого.
+pub fn get_irr() -> (u8, u8) {
+    without_interrupts(|| {
+        let mut pics = PICS.lock();
+        (pics.master.read_irr(), pics.slave.read_irr())
+    })
+}
+
+/// Сколько было ложных IRQ7 и IRQ15.
+pub fn spurious_counts() -> (u64, u64) {
+    without_interrupts(|| PICS.lock().spurious)
 }
 
 pub fn set_master_mask(mask: u8) {
-    let mut pics = PICS.lock();
-    pics.master.write_mask(mask);
+    without_interrupts(|| PICS.lock().master.write_mask(mask));
 }
 
 pub fn set_slave_mask(mask: u8) {
-    let mut pics = PICS.lock();
-    pics.slave.write_mask(mask);
+    without_interrupts(|| PICS.lock().slave.write_mask(mask));
 }
 
-pub fn send_eoi(irq: u8) {
-    let mut pics = PICS.lock();
-    pics.notify_end_of_interrupt(irq);
+pub fn mask_irq(irq: u8) {
+    without_interrupts(|| {
+        let mut pics = PICS.lock();
+        if irq < 8 {
+            let mask = pics.master.read_mask();
+            pics.master.write_mask(mask | (1 << irq));
+        } else {
+            let mask = pics.slave.read_mask();
+            pics.slave.write_mask(mask | (1 << (irq - 8)));
+        }
+    });
+}
+
+pub fn unmask_irq(irq: u8) {
+    without_interrupts(|| {
+        let mut pics = PICS.lock();
+        if irq < 8 {
+            let mask = pics.master.read_mask();
+            pics.master.write_mask(mask & !(1 << irq));
+        } else {
+            let mask = pics.slave.read_mask();
+            pics.slave.write_mask(mask & !(1 << (irq - 8)));
+            // Без каскада IRQ2 ведомый PIC до процессора не достучится
+            let mask = pics.master.read_mask();
+            pics.master.write_mask(mask & !(1 << 2));
+        }
+    });
+}
+
+pub fn mask_all() {
+    without_interrupts(|| {
+        let mut pics = PICS.lock();
+        pics.master.write_mask(0xFF);
+        pics.slave.write_mask(0xFF);
+    });
+}
+
+/// `false` — прерывание оказалось ложным и EOI не понадобился.
+pub fn send_eoi(irq: u8) -> bool {
+    without_interrupts(|| PICS.lock().notify_end_of_interrupt(irq))
+}
+
+/// IRQ7: своего драйвера нет, но ложные прерывания приходят и на закрытую
+/// линию, так что вектор нельзя оставлять пустым.
+pub extern "x86-interrupt" fn master_spurious_handler(_frame: InterruptStackFrame) {
+    irq::enter();
+    irq::end_of_interrupt(MASTER_SPURIOUS_IRQ);
+}
+
+pub extern "x86-interrupt" fn slave_spurious_handler(_frame: InterruptStackFrame) {
+    irq::enter();
+    irq::end_of_interrupt(SLAVE_SPURIOUS_IRQ);
 }
 
 pub fn init() -> Result<(), &'static str> {
-    let mut pics = PICS.lock();
-    unsafe { pics.init(); }
-    
-    if !pics.test() {
-        return Err("PIC self-test failed");
-    }
-    
-    Ok(())
+    commands::register_all(&[&commands::pic::COMMAND]);
+    without_interrupts(|| {
+        let mut pics = PICS.lock();
+        unsafe { pics.init(); }
+        
+        if !pics.test() {
+            return Err("PIC self-test failed");
+        }
+        
+        Ok(())
+    })
 }
 
 pub fn test() -> bool {
-    PICS.lock().test()
+    without_interrupts(|| PICS.lock().test())
 }
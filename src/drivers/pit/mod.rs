@@ -0,0 +1,144 @@
+//! Программируемый таймер 8253/8254 (PIT) на IRQ0.
+//!
+//! Канал 0 работает генератором частоты (режим 2) и тикает на заданной
+//! частоте. Тики считает обработчик IRQ0, он же копит время с загрузки в
+//! наносекундах, так что смена частоты не ломает монотонность. Короткие
+//! задержки отмеряются по самому счётчику канала и работают и без прерываний.
+
+use core::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use x86_64::instructions::interrupts::{self, without_interrupts};
+use x86_64::instructions::port::Port;
+use x86_64::structures::idt::InterruptStackFrame;
+use crate::commands;
+use crate::drivers::irq;
+
+pub const IRQ: u8 = 0;
+/// Частота входного генератора PIT, Гц.
+pub const BASE_FREQUENCY: u32 = 1_193_182;
+pub const DEFAULT_FREQUENCY: u32 = 1000;
+
+const CHANNEL0: u16 = 0x40;
+const COMMAND: u16 = 0x43;
+
+// Канал 0, доступ младший/старший байт, режим 2, двоичный счёт
+const CMD_CHANNEL0_RATE: u8 = 0x34;
+// Защёлкнуть текущее значение счётчика канала 0
+const CMD_CHANNEL0_LATCH: u8 = 0x00;
+
+const NANOS_PER_SECOND: u64 = 1_000_000_000;
+
+static TICKS: AtomicU64 = AtomicU64::new(0);
+static UPTIME_NS: AtomicU64 = AtomicU64::new(0);
+static NANOS_PER_TICK: AtomicU64 = AtomicU64::new(0);
+/// Делитель канала 0; 0 — таймер ещё не запущен.
+static DIVISOR: AtomicU32 = AtomicU32::new(0);
+
+pub extern "x86-interrupt" fn interrupt_handler(_frame: InterruptStackFrame) {
+    irq::enter();
+    TICKS.fetch_add(1, Ordering::Relaxed);
+    UPTIME_NS.fetch_add(NANOS_PER_TICK.load(Ordering::Relaxed), Ordering::Relaxed);
+    irq::end_of_interrupt(IRQ);
+}
+
+/// Запускает канал 0 на частоте `frequency` и разрешает IRQ0.
+pub fn init(frequency: u32) -> Result<(), &'static str> {
+    commands::register_all(&[&commands::time::SLEEP, &commands::time::UPTIME]);
+    set_frequency(frequency)?;
+    irq::unmask(IRQ);
+    Ok(())
+}
+
+/// Меняет частоту тиков. Возможны 19-1193182 Гц; настоящая частота
+/// округляется до целого делителя, её возвращает `frequency`.
+pub fn set_frequency(frequency: u32) -> Result<(), &'static str> {
+    if frequency == 0 {
+        return Err("frequency must not be zero");
+    }
+    let divisor = BASE_FREQUENCY.div_ceil(frequency);
+    if !(1..=65536).contains(&divisor) {
+        return Err("frequency must be 19-1193182 Hz");
+    }
+
+    without_interrupts(|| {
+        NANOS_PER_TICK.store(divisor as u64 * NANOS_PER_SECOND / BASE_FREQUENCY as u64, Ordering::Relaxed);
+        DIVISOR.store(divisor, Ordering::Relaxed);
+        // 65536 записывается как 0
+        let value = (divisor & 0xFFFF) as u16;
+        unsafe {
+            Port::<u8>::new(COMMAND).write(CMD_CHANNEL0_RATE);
+            let mut data = Port::<u8>::new(CHANNEL0);
+            data.write(value as u8);
+            data.write((value >> 8) as u8);
+        }
+    });
+    Ok(())
+}
+
+/// Настоящая частота тиков, 0 — таймер не запущен.
+pub fn frequency() -> u32 {
+    match DIVISOR.load(Ordering::Relaxed) {
+        0 => 0,
+        divisor => BASE_FREQUENCY / divisor,
+    }
+}
+
+pub fn ticks() -> u64 {
+    TICKS.load(Ordering::Relaxed)
+}
+
+/// Время с запуска таймера с точностью до тика.
+pub fn uptime_ns() -> u64 {
+    UPTIME_NS.load(Ordering::Relaxed)
+}
+
+pub fn uptime_ms() -> u64 {
+    uptime_ns() / 1_000_000
+}
+
+/// Спит не меньше `ms` миллисекунд, останавливая процессор между тиками.
+/// Без тиков или с запрещёнными прерываниями ждёт по счётчику PIT.
+pub fn sleep_ms(ms: u64) {
+    if DIVISOR.load(Ordering::Relaxed) == 0 || !interrupts::are_enabled() {
+        busy_wait_us(ms.saturating_mul(1000));
+        return;
+    }
+    let deadline = uptime_ns().saturating_add(ms.saturating_mul(1_000_000));
+    while uptime_ns() < deadline {
+        x86_64::instructions::hlt();
+    }
+}
+
+/// Ждёт не меньше `us` микросекунд, опрашивая счётчик канала 0. Не требует
+/// прерываний, поэтому годится для драйверов в любом контексте.
+pub fn busy_wait_us(us: u64) {
+    let reload = match DIVISOR.load(Ordering::Relaxed) {
+        // Канал 0 не запрограммирован: BIOS оставляет его считать с 65536
+        0 => 65536,
+        divisor => divisor as u64,
+    };
+    let target = us.saturating_mul(BASE_FREQUENCY as u64) / 1_000_000 + 1;
+
+    let mut elapsed = 0;
+    let mut previous = read_counter();
+    while elapsed < target {
+        core::hint::spin_loop();
+        let current = read_counter();
+        // Счётчик идёт вниз и после нуля перезагружается делителем
+        elapsed += if current <= previous {
+            previous - current
+        } else {
+            previous + reload - current
+        };
+        previous = current;
+    }
+}
+
+fn read_counter() -> u64 {
+    without_interrupts(|| unsafe {
+        Port::<u8>::new(COMMAND).write(CMD_CHANNEL0_LATCH);
+        let mut data = Port::<u8>::new(CHANNEL0);
+        let low = data.read() as u64;
+        let high = data.read() as u64;
+        low | high << 8
+    })
+}
@@ -0,0 +1,43 @@
+use core::sync::atomic::{AtomicU8, AtomicUsize, Ordering};
+
+/// Кольцевой буфер на одного писателя (обработчик IRQ) и одного читателя (основной цикл).
+pub struct ByteQueue<const N: usize> {
+    buffer: [AtomicU8; N],
+    head: AtomicUsize,
+    tail: AtomicUsize,
+}
+
+impl<const N: usize> ByteQueue<N> {
+    pub const fn new() -> Self {
+        Self {
+            buffer: [const { AtomicU8::new(0) }; N],
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn push(&self, byte: u8) -> bool {
+        let tail = self.tail.load(Ordering::Relaxed);
+        let next = (tail + 1) % N;
+        if next == self.head.load(Ordering::Acquire) {
+            return false;
+        }
+        self.buffer[tail].store(byte, Ordering::Relaxed);
+        self.tail.store(next, Ordering::Release);
+        true
+    }
+
+    pub fn pop(&self) -> Option<u8> {
+        let head = self.head.load(Ordering::Relaxed);
+        if head == self.tail.load(Ordering::Acquire) {
+            return None;
+        }
+        let byte = self.buffer[head].load(Ordering::Relaxed);
+        self.head.store((head + 1) % N, Ordering::Release);
+        Some(byte)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.head.load(Ordering::Acquire) == self.tail.load(Ordering::Acquire)
+    }
+}
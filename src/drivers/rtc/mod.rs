@@ -0,0 +1,250 @@
+//! Часы реального времени (RTC) в CMOS.
+//!
+//! Регистры выбираются записью номера в порт 0x70 и читаются из 0x71.
+//! Формат задаёт регистр состояния B: BCD или двоичный, 12 или 24 часа.
+//! Пока часы обновляют регистры, значения могут быть рваными, поэтому время
+//! читаем вне обновления и дважды, пока два чтения не совпадут.
+
+use core::fmt;
+use core::sync::atomic::{AtomicU8, Ordering};
+use x86_64::instructions::interrupts::without_interrupts;
+use x86_64::instructions::port::Port;
+use crate::acpi::fadt::Fadt;
+use crate::commands;
+use crate::spin::SpinMutex;
+
+const CMOS_ADDRESS: u16 = 0x70;
+const CMOS_DATA: u16 = 0x71;
+
+const REG_SECONDS: u8 = 0x00;
+const REG_MINUTES: u8 = 0x02;
+const REG_HOURS: u8 = 0x04;
+const REG_DAY: u8 = 0x07;
+const REG_MONTH: u8 = 0x08;
+const REG_YEAR: u8 = 0x09;
+const REG_STATUS_A: u8 = 0x0A;
+const REG_STATUS_B: u8 = 0x0B;
+// Регистры часов и состояния; регистр века, если есть, лежит выше
+const CLOCK_REGISTERS: u8 = 0x0E;
+const CMOS_REGISTERS: u8 = 0x80;
+
+const STATUS_A_UPDATING: u8 = 0x80;
+const STATUS_B_24_HOUR: u8 = 0x02;
+const STATUS_B_BINARY: u8 = 0x04;
+// Остановить обновление, пока пишем
+const STATUS_B_SET: u8 = 0x80;
+const HOUR_PM: u8 = 0x80;
+
+// Пара запись номера — чтение данных не должна перемежаться с чужой
+static CMOS: SpinMutex<()> = SpinMutex::new(());
+// Номер регистра века из FADT; 0 — его нет, век угадывается по году
+static CENTURY_REGISTER: AtomicU8 = AtomicU8::new(0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DateTime {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+const WEEKDAYS: [&str; 7] = ["Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday", "Sunday"];
+
+fn is_leap_year(year: u16) -> bool {
+    (year.is_multiple_of(4) && !year.is_multiple_of(100)) || year.is_multiple_of(400)
+}
+
+fn days_in_month(year: u16, month: u8) -> u8 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+impl DateTime {
+    /// Годы, которые RTC хранит вместе с регистром века.
+    pub fn is_valid(&self) -> bool {
+        (1900..=2099).contains(&self.year)
+            && (1..=12).contains(&self.month)
+            && self.day >= 1
+            && self.day <= days_in_month(self.year, self.month)
+            && self.hour < 24
+            && self.minute < 60
+            && self.second < 60
+    }
+
+    pub fn weekday(&self) -> &'static str {
+        // Алгоритм Сакамото, 0 — воскресенье
+        const OFFSETS: [u16; 12] = [0, 3, 2, 5, 0, 3, 5, 1, 4, 6, 2, 4];
+        let year = if self.month < 3 { self.year - 1 } else { self.year };
+        let day = (year + year / 4 - year / 100 + year / 400 + OFFSETS[self.month as usize - 1] + self.day as u16) % 7;
+        WEEKDAYS[(day as usize + 6) % 7]
+    }
+}
+
+impl fmt::Display for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )
+    }
+}
+
+/// Регистры как есть, до разбора формата.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Raw {
+    second: u8,
+    minute: u8,
+    hour: u8,
+    day: u8,
+    month: u8,
+    year: u8,
+    century: u8,
+}
+
+unsafe fn read_register(register: u8) -> u8 {
+    Port::<u8>::new(CMOS_ADDRESS).write(register);
+    Port::<u8>::new(CMOS_DATA).read()
+}
+
+unsafe fn write_register(register: u8, value: u8) {
+    Port::<u8>::new(CMOS_ADDRESS).write(register);
+    Port::<u8>::new(CMOS_DATA).write(value);
+}
+
+unsafe fn read_raw() -> Raw {
+    // Обновление длится меньше 2 мс, дольше флаг не держится
+    while read_register(REG_STATUS_A) & STATUS_A_UPDATING != 0 {
+        core::hint::spin_loop();
+    }
+    Raw {
+        second: read_register(REG_SECONDS),
+        minute: read_register(REG_MINUTES),
+        hour: read_register(REG_HOURS),
+        day: read_register(REG_DAY),
+        month: read_register(REG_MONTH),
+        year: read_register(REG_YEAR),
+        century: match CENTURY_REGISTER.load(Ordering::Relaxed) {
+            0 => 0,
+            register => read_register(register),
+        },
+    }
+}
+
+fn from_bcd(value: u8) -> u8 {
+    (value >> 4) * 10 + (value & 0x0F)
+}
+
+fn to_bcd(value: u8) -> u8 {
+    ((value / 10) << 4) | (value % 10)
+}
+
+/// Регистрирует команду `date`, находит регистр века и проверяет, что
+/// часы идут и показывают правдоподобную дату.
+pub fn init() -> Result<DateTime, &'static str> {
+    commands::register_all(&[&commands::date::COMMAND]);
+    let century = Fadt::load().map_or(0, |fadt| fadt.century);
+    if (CLOCK_REGISTERS..CMOS_REGISTERS).contains(&century) {
+        CENTURY_REGISTER.store(century, Ordering::Relaxed);
+    }
+    let now = read();
+    if now.is_valid() {
+        Ok(now)
+    } else {
+        Err("clock reports an invalid date")
+    }
+}
+
+pub fn read() -> DateTime {
+    let (raw, status_b) = without_interrupts(|| {
+        let _cmos = CMOS.lock();
+        unsafe {
+            let mut raw = read_raw();
+            loop {
+                let again = read_raw();
+                if again == raw {
+                    break;
+                }
+                raw = again;
+            }
+            (raw, read_register(REG_STATUS_B))
+        }
+    });
+
+    let binary = status_b & STATUS_B_BINARY != 0;
+    let decode = |value: u8| if binary { value } else { from_bcd(value) };
+
+    let pm = raw.hour & HOUR_PM != 0;
+    let mut hour = decode(raw.hour & !HOUR_PM);
+    if status_b & STATUS_B_24_HOUR == 0 {
+        // 12 часов: 12 AM — это 0, 12 PM — это 12
+        hour = hour % 12 + if pm { 12 } else { 0 };
+    }
+
+    let year = decode(raw.year) as u16;
+    let century = match decode(raw.century) {
+        century @ 19..=20 => century as u16,
+        // Регистра века нет: считаем, что 70-99 — прошлый век
+        _ if year >= 70 => 19,
+        _ => 20,
+    };
+
+    DateTime {
+        year: century * 100 + year,
+        month: decode(raw.month),
+        day: decode(raw.day),
+        hour,
+        minute: decode(raw.minute),
+        second: decode(raw.second),
+    }
+}
+
+/// Записывает время в том формате, в котором часы уже работают.
+pub fn set(time: &DateTime) -> Result<(), &'static str> {
+    if !time.is_valid() {
+        return Err("date or time is out of range");
+    }
+    let century_register = CENTURY_REGISTER.load(Ordering::Relaxed);
+    if century_register == 0 && !(1970..=2069).contains(&time.year) {
+        return Err("the clock has no century register, years are limited to 1970-2069");
+    }
+
+    without_interrupts(|| {
+        let _cmos = CMOS.lock();
+        unsafe {
+            let status_b = read_register(REG_STATUS_B);
+            let binary = status_b & STATUS_B_BINARY != 0;
+            let encode = |value: u8| if binary { value } else { to_bcd(value) };
+
+            let hour = if status_b & STATUS_B_24_HOUR != 0 {
+                encode(time.hour)
+            } else {
+                let pm = if time.hour >= 12 { HOUR_PM } else { 0 };
+                let hour = match time.hour % 12 {
+                    0 => 12,
+                    hour => hour,
+                };
+                encode(hour) | pm
+            };
+
+            write_register(REG_STATUS_B, status_b | STATUS_B_SET);
+            write_register(REG_SECONDS, encode(time.second));
+            write_register(REG_MINUTES, encode(time.minute));
+            write_register(REG_HOURS, hour);
+            write_register(REG_DAY, encode(time.day));
+            write_register(REG_MONTH, encode(time.month));
+            write_register(REG_YEAR, encode((time.year % 100) as u8));
+            if century_register != 0 {
+                write_register(century_register, encode((time.year / 100) as u8));
+            }
+            write_register(REG_STATUS_B, status_b & !STATUS_B_SET);
+        }
+    });
+    Ok(())
+}
@@ -0,0 +1,308 @@
+use core::fmt;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use x86_64::instructions::interrupts::without_interrupts;
+use x86_64::instructions::port::Port;
+use x86_64::structures::idt::InterruptStackFrame;
+use crate::commands;
+use crate::drivers::irq;
+use crate::drivers::queue::ByteQueue;
+use crate::spin::SpinMutex;
+
+pub const COM1_BASE: u16 = 0x3F8;
+pub const IRQ: u8 = 4;
+
+// Смещения регистров от базового порта
+const DATA: u16 = 0;
+const INTERRUPT_ENABLE: u16 = 1;
+const FIFO_CONTROL: u16 = 2;
+const LINE_CONTROL: u16 = 3;
+const MODEM_CONTROL: u16 = 4;
+const LINE_STATUS: u16 = 5;
+const SCRATCH: u16 = 7;
+
+// При DLAB = 1 первые два регистра становятся делителем частоты
+const LCR_DLAB: u8 = 0x80;
+const IER_RX_AVAILABLE: u8 = 0x01;
+const FCR_ENABLE: u8 = 0x01;
+const FCR_CLEAR_RX: u8 = 0x02;
+const FCR_CLEAR_TX: u8 = 0x04;
+const FCR_TRIGGER_14: u8 = 0xC0;
+const MCR_DTR: u8 = 0x01;
+const MCR_RTS: u8 = 0x02;
+// OUT2 на PC соединяет линию прерывания UART с PIC
+const MCR_OUT2: u8 = 0x08;
+const MCR_LOOPBACK: u8 = 0x10;
+const LSR_DATA_READY: u8 = 0x01;
+const LSR_OVERRUN: u8 = 0x02;
+const LSR_THR_EMPTY: u8 = 0x20;
+
+const UART_CLOCK: u32 = 115200;
+const QUEUE_SIZE: usize = 256;
+// Без подключённого терминала THR может не освобождаться, тогда байт теряется
+const TRANSMIT_SPINS: usize = 100_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Parity {
+    None,
+    Odd,
+    Even,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineConfig {
+    pub baud: u32,
+    /// 5-8 бит данных.
+    pub data_bits: u8,
+    pub parity: Parity,
+    /// 1 или 2 стоп-бита.
+    pub stop_bits: u8,
+}
+
+impl LineConfig {
+    /// 115200 8N1, как ждёт `qemu -serial stdio`.
+    pub const DEFAULT: LineConfig = LineConfig {
+        baud: 115200,
+        data_bits: 8,
+        parity: Parity::None,
+        stop_bits: 1,
+    };
+
+    fn divisor(&self) -> Option<u16> {
+        if self.baud == 0 || !UART_CLOCK.is_multiple_of(self.baud) {
+            return None;
+        }
+        u16::try_from(UART_CLOCK / self.baud).ok()
+    }
+
+    fn line_control(&self) -> Option<u8> {
+        if !(5..=8).contains(&self.data_bits) || !(1..=2).contains(&self.stop_bits) {
+            return None;
+        }
+        let parity = match self.parity {
+            Parity::None => 0x00,
+            Parity::Odd => 0x08,
+            Parity::Even => 0x18,
+        };
+        Some((self.data_bits - 5) | (self.stop_bits - 1) << 2 | parity)
+    }
+}
+
+impl fmt::Display for LineConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parity = match self.parity {
+            Parity::None => 'N',
+            Parity::Odd => 'O',
+            Parity::Even => 'E',
+        };
+        write!(f, "{} {}{}{}", self.baud, self.data_bits, parity, self.stop_bits)
+    }
+}
+
+pub struct Uart16550 {
+    base: u16,
+    config: LineConfig,
+    present: bool,
+}
+
+impl Uart16550 {
+    pub const fn new(base: u16) -> Self {
+        Self {
+            base,
+            config: LineConfig::DEFAULT,
+            present: false,
+        }
+    }
+
+    fn read(&self, register: u16) -> u8 {
+        unsafe { Port::<u8>::new(self.base + register).read() }
+    }
+
+    fn write(&mut self, register: u16, value: u8) {
+        unsafe { Port::<u8>::new(self.base + register).write(value) }
+    }
+
+    /// Настраивает линию и FIFO и проверяет чип в режиме петли.
+    /// Прерывания остаются выключенными, их включает `enable_interrupts`.
+    pub fn init(&mut self, config: LineConfig) -> Result<(), &'static str> {
+        let divisor = config.divisor().ok_or("unsupported baud rate")?;
+        let line_control = config.line_control().ok_or("unsupported line settings")?;
+
+        // На месте отсутствующего порта шина отдаёт 0xFF
+        self.write(SCRATCH, 0x5A);
+        if self.read(SCRATCH) != 0x5A {
+            self.present = false;
+            return Err("no UART at this port");
+        }
+
+        self.write(INTERRUPT_ENABLE, 0);
+        self.write(LINE_CONTROL, LCR_DLAB);
+        self.write(DATA, divisor as u8);
+        self.write(INTERRUPT_ENABLE, (divisor >> 8) as u8);
+        self.write(LINE_CONTROL, line_control);
+        self.write(FIFO_CONTROL, FCR_ENABLE | FCR_CLEAR_RX | FCR_CLEAR_TX | FCR_TRIGGER_14);
+
+        self.write(MODEM_CONTROL, MCR_LOOPBACK | MCR_RTS);
+        self.write(DATA, 0xAE);
+        let echoed = self.read(DATA);
+        self.write(MODEM_CONTROL, MCR_DTR | MCR_RTS | MCR_OUT2);
+        if echoed != 0xAE {
+            self.present = false;
+            return Err("loopback test failed");
+        }
+
+        self.config = config;
+        self.present = true;
+        Ok(())
+    }
+
+    pub fn is_present(&self) -> bool {
+        self.present
+    }
+
+    pub fn config(&self) -> LineConfig {
+        self.config
+    }
+
+    pub fn set_interrupts(&mut self, enabled: bool) {
+        if self.present {
+            self.write(INTERRUPT_ENABLE, if enabled { IER_RX_AVAILABLE } else { 0 });
+        }
+    }
+
+    pub fn write_byte(&mut self, byte: u8) {
+        if !self.present {
+            return;
+        }
+        for _ in 0..TRANSMIT_SPINS {
+            if self.read(LINE_STATUS) & LSR_THR_EMPTY != 0 {
+                self.write(DATA, byte);
+                return;
+            }
+            core::hint::spin_loop();
+        }
+    }
+
+    /// Забирает всё из приёмного FIFO.
+    fn drain(&mut self, mut out: impl FnMut(u8)) {
+        loop {
+            let status = self.read(LINE_STATUS);
+            if status & LSR_OVERRUN != 0 {
+                OVERRUNS.fetch_add(1, Ordering::Relaxed);
+            }
+            if status & LSR_DATA_READY == 0 {
+                break;
+            }
+            out(self.read(DATA));
+        }
+    }
+}
+
+/// Терминалы ждут `\r\n`, а ядро пишет просто `\n`.
+impl fmt::Write for Uart16550 {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for byte in s.bytes() {
+            if byte == b'\n' {
+                self.write_byte(b'\r');
+            }
+            self.write_byte(byte);
+        }
+        Ok(())
+    }
+}
+
+pub static COM1: SpinMutex<Uart16550> = SpinMutex::new(Uart16550::new(COM1_BASE));
+
+static RX_QUEUE: ByteQueue<QUEUE_SIZE> = ByteQueue::new();
+static RECEIVED: AtomicUsize = AtomicUsize::new(0);
+static DROPPED: AtomicUsize = AtomicUsize::new(0);
+static OVERRUNS: AtomicUsize = AtomicUsize::new(0);
+static INTERRUPTS: AtomicBool = AtomicBool::new(false);
+// Копия `present` для обработчика паники, которому нельзя брать COM1
+static PRESENT: AtomicBool = AtomicBool::new(false);
+
+pub extern "x86-interrupt" fn interrupt_handler(_frame: InterruptStackFrame) {
+    irq::enter();
+    // Отдельный экземпляр без блокировки: обработчик не должен ждать того,
+    // кто сейчас печатает через COM1
+    Uart16550::new(COM1_BASE).drain(|byte| {
+        RECEIVED.fetch_add(1, Ordering::Relaxed);
+        if !RX_QUEUE.push(byte) {
+            DROPPED.fetch_add(1, Ordering::Relaxed);
+        }
+    });
+    irq::end_of_interrupt(IRQ);
+}
+
+pub fn init() -> Result<(), &'static str> {
+    commands::register_all(&[&commands::serial::COMMAND]);
+    configure(LineConfig::DEFAULT)
+}
+
+/// Включает приём по IRQ4. Вызывать после настройки PIC.
+pub fn enable_interrupts() {
+    without_interrupts(|| {
+        let mut com1 = COM1.lock();
+        if !com1.is_present() {
+            return;
+        }
+        com1.set_interrupts(true);
+        INTERRUPTS.store(true, Ordering::Relaxed);
+    });
+    if INTERRUPTS.load(Ordering::Relaxed) {
+        irq::unmask(IRQ);
+    }
+}
+
+pub fn configure(config: LineConfig) -> Result<(), &'static str> {
+    without_interrupts(|| {
+        let mut com1 = COM1.lock();
+        let result = com1.init(config);
+        PRESENT.store(com1.is_present(), Ordering::Relaxed);
+        com1.set_interrupts(INTERRUPTS.load(Ordering::Relaxed));
+        result
+    })
+}
+
+pub fn has_input() -> bool {
+    !RX_QUEUE.is_empty()
+}
+
+pub fn read_byte() -> Option<u8> {
+    RX_QUEUE.pop()
+}
+
+pub fn write_fmt(args: fmt::Arguments) {
+    use core::fmt::Write;
+    let _ = COM1.lock().write_fmt(args);
+}
+
+pub struct Stats {
+    pub received: usize,
+    pub dropped: usize,
+    pub overruns: usize,
+    pub interrupts: bool,
+}
+
+pub fn stats() -> Stats {
+    Stats {
+        received: RECEIVED.load(Ordering::Relaxed),
+        dropped: DROPPED.load(Ordering::Relaxed),
+        overruns: OVERRUNS.load(Ordering::Relaxed),
+        interrupts: INTERRUPTS.load(Ordering::Relaxed),
+    }
+}
+
+/// Для обработчиков исключений: COM1 мог держать тот, кто упал.
+///
+/// # Safety
+/// Тот, кто держал COM1, больше не должен им пользоваться.
+pub unsafe fn force_unlock() {
+    COM1.force_unlock();
+}
+
+/// Писатель для обработчика паники: COM1 может быть заблокирован тем, кто упал.
+pub fn panic_writer() -> Uart16550 {
+    let mut uart = Uart16550::new(COM1_BASE);
+    uart.present = PRESENT.load(Ordering::Relaxed);
+    uart
+}
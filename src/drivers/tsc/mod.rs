@@ -0,0 +1,115 @@
+//! Счётчик тактов процессора (TSC) и отметки времени в наносекундах.
+//!
+//! Частоту TSC процессор не сообщает, поэтому при загрузке она измеряется по
+//! PIT: сколько тактов прошло за известный интервал. HPET ядро пока не знает.
+//! На старых процессорах TSC идёт с частотой ядра и плывёт вместе с ней;
+//! надёжны только отметки с инвариантным TSC (CPUID 0x80000007, EDX бит 8).
+
+use core::arch::x86_64::{__cpuid, _rdtsc};
+use core::ops::{Add, Sub};
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use core::time::Duration;
+use crate::drivers::pit;
+
+// Интервал одного замера и сколько замеров делать
+const CALIBRATION_US: u64 = 10_000;
+const CALIBRATION_ROUNDS: usize = 5;
+
+const NANOS_PER_SECOND: u128 = 1_000_000_000;
+
+/// Частота TSC в Гц, 0 — ещё не откалиброван.
+static FREQUENCY: AtomicU64 = AtomicU64::new(0);
+static INVARIANT: AtomicBool = AtomicBool::new(false);
+
+fn has_tsc() -> bool {
+    __cpuid(1).edx & (1 << 4) != 0
+}
+
+fn detect_invariant() -> bool {
+    __cpuid(0x8000_0000).eax >= 0x8000_0007 && __cpuid(0x8000_0007).edx & (1 << 8) != 0
+}
+
+pub fn read() -> u64 {
+    unsafe { _rdtsc() }
+}
+
+/// Измеряет частоту TSC по каналу 0 PIT. Работает и с запрещёнными
+/// прерываниями; занимает около 50 мс.
+pub fn init() -> Result<(), &'static str> {
+    if !has_tsc() {
+        return Err("CPU has no TSC");
+    }
+    INVARIANT.store(detect_invariant(), Ordering::Relaxed);
+
+    // Ожидание PIT может только затянуться, а не оборваться раньше, поэтому
+    // лишние такты лишь завышают частоту: берём наименьший замер
+    let mut frequency = u64::MAX;
+    for _ in 0..CALIBRATION_ROUNDS {
+        let start = read();
+        pit::busy_wait_us(CALIBRATION_US);
+        let ticks = read().wrapping_sub(start);
+        frequency = frequency.min(ticks * 1_000_000 / CALIBRATION_US);
+    }
+    if frequency == 0 {
+        return Err("TSC does not advance");
+    }
+    FREQUENCY.store(frequency, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Частота TSC в Гц, 0 — `init` ещё не вызывали или он не удался.
+pub fn frequency() -> u64 {
+    FREQUENCY.load(Ordering::Relaxed)
+}
+
+pub fn is_invariant() -> bool {
+    INVARIANT.load(Ordering::Relaxed)
+}
+
+fn ticks_to_nanos(ticks: u64) -> u64 {
+    match frequency() {
+        0 => 0,
+        frequency => (ticks as u128 * NANOS_PER_SECOND / frequency as u128) as u64,
+    }
+}
+
+fn nanos_to_ticks(nanos: u64) -> u64 {
+    (nanos as u128 * frequency() as u128 / NANOS_PER_SECOND) as u64
+}
+
+/// Момент времени по TSC. Как и `std::time::Instant`, сравним только с
+/// другими отметками; до калибровки все интервалы нулевые.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Instant(u64);
+
+impl Instant {
+    pub fn now() -> Instant {
+        Instant(read())
+    }
+
+    /// Интервал от `earlier` до этой отметки; ноль, если `earlier` позже.
+    pub fn duration_since(&self, earlier: Instant) -> Duration {
+        Duration::from_nanos(ticks_to_nanos(self.0.saturating_sub(earlier.0)))
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        Instant::now().duration_since(*self)
+    }
+}
+
+impl Add<Duration> for Instant {
+    type Output = Instant;
+
+    fn add(self, duration: Duration) -> Instant {
+        let nanos = u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX);
+        Instant(self.0.saturating_add(nanos_to_ticks(nanos)))
+    }
+}
+
+impl Sub for Instant {
+    type Output = Duration;
+
+    fn sub(self, earlier: Instant) -> Duration {
+        self.duration_since(earlier)
+    }
+}
@@ -0,0 +1,100 @@
+//! GDT и TSS.
+//!
+//! GDT из `boot.nasm` нужна только для перехода в long mode, дальше ядро
+//! живёт на этой. У каждого процессора своя GDT: общие сегменты ядра и
+//! пользователя плюс дескриптор его TSS. Раскладка у всех одинаковая, так что
+//! селекторы тоже общие. Пользовательские сегменты стоят в порядке, который
+//! нужен `sysret`: сначала данные, потом код.
+//!
+//! TSS нужен ради таблицы стеков прерываний (IST): двойная ошибка всегда
+//! выполняется на своём стеке. Иначе при переполнении стека ядра процессор
+//! не смог бы положить кадр исключения, и машина ушла бы в тройную ошибку
+//! и перезагрузку без всякого отчёта.
+
+use core::ptr::{addr_of, addr_of_mut};
+use core::sync::atomic::{AtomicBool, Ordering};
+use lazy_static::lazy_static;
+use x86_64::instructions::segmentation::{Segment, CS, DS, ES, FS, GS, SS};
+use x86_64::instructions::tables::load_tss;
+use x86_64::structures::gdt::{Descriptor, GlobalDescriptorTable, SegmentSelector};
+use x86_64::structures::tss::TaskStateSegment;
+use x86_64::{PrivilegeLevel, VirtAddr};
+
+pub const KERNEL_CODE: SegmentSelector = SegmentSelector::new(1, PrivilegeLevel::Ring0);
+pub const KERNEL_DATA: SegmentSelector = SegmentSelector::new(2, PrivilegeLevel::Ring0);
+pub const USER_DATA: SegmentSelector = SegmentSelector::new(3, PrivilegeLevel::Ring3);
+pub const USER_CODE: SegmentSelector = SegmentSelector::new(4, PrivilegeLevel::Ring3);
+/// Дескриптор TSS занимает две записи, 5 и 6.
+pub const TSS: SegmentSelector = SegmentSelector::new(5, PrivilegeLevel::Ring0);
+
+/// Сколько процессоров могут получить свою GDT.
+pub const MAX_CPUS: usize = 8;
+
+/// Номер стека в IST для `#DF`.
+pub const DOUBLE_FAULT_IST_INDEX: u16 = 0;
+
+const DOUBLE_FAULT_STACK_SIZE: usize = 4096 * 4;
+
+#[repr(align(16))]
+struct Stack([u8; DOUBLE_FAULT_STACK_SIZE]);
+
+static mut DOUBLE_FAULT_STACK: Stack = Stack([0; DOUBLE_FAULT_STACK_SIZE]);
+
+// В GDT процессора пишем только до установки, потом таблица лишь читается
+static mut GDTS: [GlobalDescriptorTable; MAX_CPUS] = [const { GlobalDescriptorTable::new() }; MAX_CPUS];
+static INSTALLED: [AtomicBool; MAX_CPUS] = [const { AtomicBool::new(false) }; MAX_CPUS];
+
+lazy_static! {
+    static ref BOOT_TSS: TaskStateSegment = {
+        // Стек растёт вниз, в IST кладётся его верхний край
+        let stack_start = VirtAddr::from_ptr(unsafe { addr_of!(DOUBLE_FAULT_STACK.0) });
+        new_tss(stack_start + DOUBLE_FAULT_STACK_SIZE as u64)
+    };
+}
+
+/// TSS со стеком для `#DF`; `double_fault_stack` — верхний край стека.
+/// RSP0 для перехода из ring 3 вызывающий заполняет сам.
+pub fn new_tss(double_fault_stack: VirtAddr) -> TaskStateSegment {
+    let mut tss = TaskStateSegment::new();
+    tss.interrupt_stack_table[DOUBLE_FAULT_IST_INDEX as usize] = double_fault_stack;
+    tss
+}
+
+/// Собирает GDT процессора `cpu` с его `tss` и загружает её вместе с
+/// сегментными регистрами и TR. Вызывается один раз на самом этом процессоре.
+pub fn install(cpu: usize, tss: &'static TaskStateSegment) -> Result<(), &'static str> {
+    let installed = INSTALLED.get(cpu).ok_or("CPU number is out of range")?;
+    if installed.swap(true, Ordering::AcqRel) {
+        return Err("GDT for this CPU is already installed");
+    }
+
+    let gdt = unsafe { &mut *addr_of_mut!(GDTS[cpu]) };
+    let selectors = [
+        gdt.add_entry(Descriptor::kernel_code_segment()),
+        gdt.add_entry(Descriptor::kernel_data_segment()),
+        gdt.add_entry(Descriptor::user_data_segment()),
+        gdt.add_entry(Descriptor::user_code_segment()),
+        gdt.add_entry(Descriptor::tss_segment(tss)),
+    ];
+    debug_assert_eq!(selectors, [KERNEL_CODE, KERNEL_DATA, USER_DATA, USER_CODE, TSS]);
+
+    let gdt = unsafe { &*addr_of!(GDTS[cpu]) };
+    gdt.load();
+    unsafe {
+        CS::set_reg(KERNEL_CODE);
+        SS::set_reg(KERNEL_DATA);
+        DS::set_reg(KERNEL_DATA);
+        ES::set_reg(KERNEL_DATA);
+        // Базы FS и GS задаются через MSR, селекторы им не нужны
+        FS::set_reg(SegmentSelector(0));
+        GS::set_reg(SegmentSelector(0));
+        load_tss(TSS);
+    }
+    Ok(())
+}
+
+/// Ставит GDT загрузочного процессора. До `interrupts::init`: IDT
+/// ссылается на стек из IST.
+pub fn init() -> Result<(), &'static str> {
+    install(0, &BOOT_TSS)
+}
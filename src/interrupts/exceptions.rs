@@ -0,0 +1,169 @@
+use core::fmt::Write;
+use x86_64::instructions::{hlt, interrupts};
+use x86_64::registers::control::Cr2;
+use x86_64::structures::idt::{InterruptDescriptorTable, InterruptStackFrame, PageFaultErrorCode};
+use crate::drivers::{irq, serial};
+use crate::vga_buffer::{self, Color};
+use crate::{gdt, memory, spin};
+use crate::print;
+
+use super::guard;
+
+macro_rules! exception {
+    ($name:ident, $vector:expr, $mnemonic:expr, $desc:expr) => {
+        extern "x86-interrupt" fn $name(mut frame: InterruptStackFrame) {
+            fault(&mut frame, $vector, $mnemonic, $desc, None);
+        }
+    };
+    ($name:ident, $vector:expr, $mnemonic:expr, $desc:expr, error_code) => {
+        extern "x86-interrupt" fn $name(mut frame: InterruptStackFrame, error_code: u64) {
+            fault(&mut frame, $vector, $mnemonic, $desc, Some(error_code));
+        }
+    };
+}
+
+exception!(divide_error, 0, "#DE", "Divide Error");
+exception!(overflow, 4, "#OF", "Overflow");
+exception!(bound_range_exceeded, 5, "#BR", "Bound Range Exceeded");
+exception!(invalid_opcode, 6, "#UD", "Invalid Opcode");
+exception!(device_not_available, 7, "#NM", "Device Not Available");
+exception!(coprocessor_segment_overrun, 9, "CSO", "Coprocessor Segment Overrun");
+exception!(invalid_tss, 10, "#TS", "Invalid TSS", error_code);
+exception!(segment_not_present, 11, "#NP", "Segment Not Present", error_code);
+exception!(stack_segment_fault, 12, "#SS", "Stack-Segment Fault", error_code);
+exception!(general_protection_fault, 13, "#GP", "General Protection Fault", error_code);
+exception!(x87_floating_point, 16, "#MF", "x87 Floating-Point Exception");
+exception!(alignment_check, 17, "#AC", "Alignment Check", error_code);
+exception!(simd_floating_point, 19, "#XM", "SIMD Floating-Point Exception");
+exception!(virtualization, 20, "#VE", "Virtualization Exception");
+exception!(cp_protection, 21, "#CP", "Control Protection Exception", error_code);
+exception!(hv_injection, 28, "#HV", "Hypervisor Injection Exception");
+exception!(vmm_communication, 29, "#VC", "VMM Communication Exception", error_code);
+exception!(security, 30, "#SX", "Security Exception", error_code);
+
+/// NMI не ошибка выполняемого кода: сообщаем и возвращаемся туда, где были.
+extern "x86-interrupt" fn non_maskable_interrupt(frame: InterruptStackFrame) {
+    let rip = frame.instruction_pointer.as_u64();
+    if spin::held_locks() == 0 {
+        print!(("\nNMI at 0x{:016X}", rip), fg: Color::Yellow);
+    } else {
+        // Прерванный код держит замки, возможно и замки вывода: отнимать их
+        // нельзя, ведь он продолжит работу, поэтому пишем в COM1 напрямую
+        let _ = write!(serial::panic_writer(), "\nNMI at 0x{:016X}", rip);
+    }
+}
+
+extern "x86-interrupt" fn debug(frame: InterruptStackFrame) {
+    print!(("\nDEBUG trap at 0x{:016X}", frame.instruction_pointer.as_u64()), fg: Color::Yellow);
+}
+
+extern "x86-interrupt" fn breakpoint(frame: InterruptStackFrame) {
+    print!(("\nBREAKPOINT at 0x{:016X}", frame.instruction_pointer.as_u64()), fg: Color::Yellow);
+}
+
+extern "x86-interrupt" fn page_fault(mut frame: InterruptStackFrame, error_code: PageFaultErrorCode) {
+    if memory::is_stack_guard(Cr2::read_raw()) {
+        // Команду не спасти: её стек испорчен, вернуться некуда
+        report(&frame, 14, "#PF", "Page Fault", Some(error_code.bits()));
+        report_stack_overflow();
+        halt()
+    }
+    fault(&mut frame, 14, "#PF", "Page Fault", Some(error_code.bits()));
+}
+
+extern "x86-interrupt" fn double_fault(frame: InterruptStackFrame, error_code: u64) -> ! {
+    report(&frame, 8, "#DF", "Double Fault", Some(error_code));
+    // Обычно это #PF на странице-стороже: кадр для него положить было некуда
+    if memory::is_stack_guard(Cr2::read_raw()) || memory::is_stack_guard(frame.stack_pointer.as_u64()) {
+        report_stack_overflow();
+    }
+    halt()
+}
+
+extern "x86-interrupt" fn machine_check(frame: InterruptStackFrame) -> ! {
+    report(&frame, 18, "#MC", "Machine Check", None);
+    halt()
+}
+
+pub(super) fn install(idt: &mut InterruptDescriptorTable) {
+    idt.divide_error.set_handler_fn(divide_error);
+    idt.debug.set_handler_fn(debug);
+    idt.non_maskable_interrupt.set_handler_fn(non_maskable_interrupt);
+    idt.breakpoint.set_handler_fn(breakpoint);
+    idt.overflow.set_handler_fn(overflow);
+    idt.bound_range_exceeded.set_handler_fn(bound_range_exceeded);
+    idt.invalid_opcode.set_handler_fn(invalid_opcode);
+    idt.device_not_available.set_handler_fn(device_not_available);
+    unsafe {
+        idt.double_fault.set_handler_fn(double_fault).set_stack_index(gdt::DOUBLE_FAULT_IST_INDEX);
+    }
+    idt[9].set_handler_fn(coprocessor_segment_overrun);
+    idt.invalid_tss.set_handler_fn(invalid_tss);
+    idt.segment_not_present.set_handler_fn(segment_not_present);
+    idt.stack_segment_fault.set_handler_fn(stack_segment_fault);
+    idt.general_protection_fault.set_handler_fn(general_protection_fault);
+    idt.page_fault.set_handler_fn(page_fault);
+    idt.x87_floating_point.set_handler_fn(x87_floating_point);
+    idt.alignment_check.set_handler_fn(alignment_check);
+    idt.machine_check.set_handler_fn(machine_check);
+    idt.simd_floating_point.set_handler_fn(simd_floating_point);
+    idt.virtualization.set_handler_fn(virtualization);
+    idt.cp_protection_exception.set_handler_fn(cp_protection);
+    idt.hv_injection_exception.set_handler_fn(hv_injection);
+    idt.vmm_communication_exception.set_handler_fn(vmm_communication);
+    idt.security_exception.set_handler_fn(security);
+    // Векторы 15, 22-27 и 31 зарезервированы, x86_64 не даёт их заполнить
+}
+
+fn fault(frame: &mut InterruptStackFrame, vector: u8, mnemonic: &str, desc: &str, error_code: Option<u64>) {
+    report(frame, vector, mnemonic, desc, error_code);
+
+    // Замки вывода report уже отпустил. Любой другой замок команда взяла и не
+    // отпустит: после возврата в шелл первый же, кто за ним придёт, зависнет
+    let held = spin::held_locks();
+    if held == 0 && guard::recover(frame) {
+        print!(("\nCommand aborted"), fg: Color::Yellow);
+        return;
+    }
+    if guard::is_armed() {
+        if irq::in_handler() {
+            print!(("\nCannot abort the command: the fault is in an IRQ handler"), fg: Color::LightRed);
+        } else if held != 0 {
+            print!(("\nCannot abort the command: it holds {} kernel lock(s)", held), fg: Color::LightRed);
+        }
+    }
+
+    halt()
+}
+
+fn report(frame: &InterruptStackFrame, vector: u8, mnemonic: &str, desc: &str, error_code: Option<u64>) {
+    // Исключение могло прилететь посреди печати, ждать эти локи бессмысленно
+    unsafe {
+        vga_buffer::force_unlock();
+        serial::force_unlock();
+    }
+
+    print!(("\n\nEXCEPTION: {} ({}, vector {})", desc, mnemonic, vector), fg: Color::LightRed);
+    if let Some(code) = error_code {
+        print!(("\n  Error code: 0x{:X}", code), fg: Color::White);
+    }
+    print!(("\n  RIP:    0x{:016X}", frame.instruction_pointer.as_u64()), fg: Color::White);
+    print!(("\n  CS:     0x{:04X}", frame.code_segment), fg: Color::White);
+    print!(("\n  RFLAGS: 0x{:016X}", frame.cpu_flags), fg: Color::White);
+    print!(("\n  RSP:    0x{:016X}", frame.stack_pointer.as_u64()), fg: Color::White);
+    print!(("\n  CR2:    0x{:016X}", Cr2::read_raw()), fg: Color::White);
+}
+
+fn report_stack_overflow() {
+    let (bottom, top) = memory::kernel_stack();
+    print!(("\nKernel stack overflow: "), fg: Color::LightRed);
+    print!(("the stack 0x{:X}-0x{:X} ran into its guard page", bottom.as_u64(), top.as_u64()), fg: Color::White);
+}
+
+fn halt() -> ! {
+    print!(("\nSystem halted."), fg: Color::LightRed);
+    interrupts::disable();
+    loop {
+        hlt();
+    }
+}
@@ -0,0 +1,96 @@
+//! Восстановление после исключений в командах шелла.
+//!
+//! `run_guarded` запоминает стек перед вызовом, и если внутри случится
+//! исключение, обработчик подменяет RIP/RSP в кадре прерывания так, что
+//! `iretq` возвращает нас прямо из `guarded_call` с ненулевым результатом.
+
+use core::arch::global_asm;
+use core::sync::atomic::{AtomicU64, Ordering};
+use x86_64::structures::idt::InterruptStackFrame;
+use x86_64::VirtAddr;
+use crate::drivers::irq;
+
+global_asm!(
+    ".global guarded_call",
+    "guarded_call:",
+    "    push rbp",
+    "    push rbx",
+    "    push r12",
+    "    push r13",
+    "    push r14",
+    "    push r15",
+    "    sub rsp, 8",
+    "    mov [rdx], rsp",
+    "    mov rax, rdi",
+    "    mov rdi, rsi",
+    "    call rax",
+    "    xor eax, eax",
+    "2:",
+    "    add rsp, 8",
+    "    pop r15",
+    "    pop r14",
+    "    pop r13",
+    "    pop r12",
+    "    pop rbx",
+    "    pop rbp",
+    "    ret",
+    ".global guarded_recover",
+    "guarded_recover:",
+    "    mov eax, 1",
+    "    jmp 2b",
+);
+
+extern "C" {
+    fn guarded_call(entry: extern "C" fn(*mut u8), data: *mut u8, rsp_slot: *mut u64) -> u64;
+    fn guarded_recover();
+}
+
+static GUARD_RSP: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, Clone, Copy)]
+pub struct Faulted;
+
+pub fn run_guarded<F: FnOnce()>(f: F) -> Result<(), Faulted> {
+    extern "C" fn trampoline<F: FnOnce()>(data: *mut u8) {
+        let f = unsafe { (*(data as *mut Option<F>)).take() };
+        if let Some(f) = f {
+            f();
+        }
+    }
+
+    let mut f = Some(f);
+    let previous = GUARD_RSP.load(Ordering::SeqCst);
+    let faulted = unsafe {
+        guarded_call(trampoline::<F>, &mut f as *mut Option<F> as *mut u8, GUARD_RSP.as_ptr())
+    };
+    GUARD_RSP.store(previous, Ordering::SeqCst);
+
+    if faulted != 0 {
+        Err(Faulted)
+    } else {
+        Ok(())
+    }
+}
+
+/// Команда выполняется под `run_guarded`, и упавшую можно прервать.
+pub(super) fn is_armed() -> bool {
+    GUARD_RSP.load(Ordering::SeqCst) != 0
+}
+
+/// Перенаправляет кадр исключения в точку восстановления, если она есть.
+/// Из обработчика IRQ не уходим: прыжок мимо его EOI оставил бы линию и
+/// все линии ниже по приоритету закрытыми.
+pub(super) fn recover(frame: &mut InterruptStackFrame) -> bool {
+    let rsp = GUARD_RSP.load(Ordering::SeqCst);
+    if rsp == 0 || irq::in_handler() {
+        return false;
+    }
+
+    unsafe {
+        frame.as_mut().update(|f| {
+            f.instruction_pointer = VirtAddr::new(guarded_recover as *const () as u64);
+            f.stack_pointer = VirtAddr::new(rsp);
+        });
+    }
+    true
+}
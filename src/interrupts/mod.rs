@@ -0,0 +1,30 @@
+use lazy_static::lazy_static;
+use x86_64::structures::idt::InterruptDescriptorTable;
+use crate::drivers::{apic, keyboard, pic, pit, serial};
+
+mod exceptions;
+mod guard;
+
+pub use guard::run_guarded;
+
+lazy_static! {
+    static ref IDT: InterruptDescriptorTable = {
+        let mut idt = InterruptDescriptorTable::new();
+        exceptions::install(&mut idt);
+        idt[irq_vector(pit::IRQ)].set_handler_fn(pit::interrupt_handler);
+        idt[irq_vector(keyboard::IRQ)].set_handler_fn(keyboard::interrupt_handler);
+        idt[irq_vector(serial::IRQ)].set_handler_fn(serial::interrupt_handler);
+        idt[irq_vector(pic::MASTER_SPURIOUS_IRQ)].set_handler_fn(pic::master_spurious_handler);
+        idt[irq_vector(pic::SLAVE_SPURIOUS_IRQ)].set_handler_fn(pic::slave_spurious_handler);
+        idt[apic::SPURIOUS_VECTOR as usize].set_handler_fn(apic::spurious_handler);
+        idt
+    };
+}
+
+pub const fn irq_vector(irq: u8) -> usize {
+    (pic::IRQ_OFFSET + irq) as usize
+}
+
+pub fn init() {
+    IDT.load();
+}
@@ -1,14 +1,22 @@
 #![no_std]
 #![no_main]
 #![feature(alloc_error_handler)]
+#![feature(abi_x86_interrupt)]
 
 extern crate alloc;
 
 extern crate rlibc;
 
+mod acpi;
 mod allocator;
+mod console;
 mod drivers;
+pub mod gdt;
+mod interrupts;
+mod memory;
+mod multiboot;
 mod port;
+mod shell;
 mod spin;
 mod vga_buffer;
 mod handle_command;
@@ -17,156 +25,66 @@ pub mod commands;
 pub use port::{inb, outb, inw, outw, inl, outl};
 
 use core::panic::PanicInfo;
-use handle_command::handle_command;
-use vga_buffer::{Color, WRITER};
+use drivers::keyboard;
+use vga_buffer::Color;
 
-pub use drivers::pic;
+pub use drivers::{pic, serial};
 extern crate volatile;
 
-extern "C" {
-    fn read_keyboard_input() -> u8;
-}
-
 #[macro_export]
 macro_rules! print {
     (($($arg:tt)*)) => ({
-        $crate::vga_buffer::print(format_args!($($arg)*), None, None);
+        $crate::console::print(format_args!($($arg)*), None, None);
     });
     (($($arg:tt)*), fg: $fg:expr) => ({
-        $crate::vga_buffer::print(format_args!($($arg)*), Some($fg), None);
+        $crate::console::print(format_args!($($arg)*), Some($fg), None);
     });
     (($($arg:tt)*), bg: $bg:expr) => ({
-        $crate::vga_buffer::print(format_args!($($arg)*), None, Some($bg));
+        $crate::console::print(format_args!($($arg)*), None, Some($bg));
     });
     (($($arg:tt)*), fg: $fg:expr, bg: $bg:expr) => ({
-        $crate::vga_buffer::print(format_args!($($arg)*), Some($fg), Some($bg));
+        $crate::console::print(format_args!($($arg)*), Some($fg), Some($bg));
     });
 }
 
-const INPUT_BUFFER_SIZE: usize = 128;
-static mut INPUT_BUFFER: [u8; INPUT_BUFFER_SIZE] = [0; INPUT_BUFFER_SIZE];
-static mut INPUT_BUFFER_INDEX: usize = 0;
-
-const SCANCODE_TABLE: [char; 128] = [
-    '?', '?', '1', '2', '3', '4', '5', '6', 
-    '7', '8', '9', '0', '-', '=', '?', '?', 
-    'q', 'w', 'e', 'r', 't', 'y', 'u', 'i', 
-    'o', 'p', '[', ']', '?', '?', 'a', 's', 
-    'd', 'f', 'g', 'h', 'j', 'k', 'l', ';', 
-    '\'', '`', '?', '\\', 'z', 'x', 'c', 'v', 
-    'b', 'n', 'm', ',', '.', '/', '?', '?', 
-    '?', ' ', '?', '?', '?', '?', '?', '?', 
-    '?', '?', '?', '?', '?', '?', '?', '?', 
-    '?', '?', '?', '?', '?', '?', '?', '?',
-    '?', '?', '?', '?', '?', '?', '?', '?', 
-    '?', '?', '?', '?', '?', '?', '?', '?', 
-    '?', '?', '?', '?', '?', '?', '?', '?', 
-    '?', '?', '?', '?', '?', '?', '?', '?', 
-    '?', '?', '?', '?', '?', '?', '?', '?', 
-    '?', '?', '?', '?', '?', '?', '?', '?',
-];
-
-const SCANCODE_TABLE_SHIFT: [char; 128] = [
-    '?', '?', '!', '@', '#', '$', '%', '^', 
-    '&', '*', '(', ')', '_', '+', '?', '?', 
-    'Q', 'W', 'E', 'R', 'T', 'Y', 'U', 'I', 
-    'O', 'P', '{', '}', '?', '?', 'A', 'S', 
-    'D', 'F', 'G', 'H', 'J', 'K', 'L', ':',
-    '"', '~', '?', '|', 'Z', 'X', 'C', 'V', 
-    'B', 'N', 'M', '<', '>', '?', '?', '?',
-    '?', ' ', '?', '?', '?', '?', '?', '?', 
-    '?', '?', '?', '?', '?', '?', '?', '?',
-    '?', '?', '?', '?', '?', '?', '?', '?', 
-    '?', '?', '?', '?', '?', '?', '?', '?', 
-    '?', '?', '?', '?', '?', '?', '?', '?',
-    '?', '?', '?', '?', '?', '?', '?', '?',
-    '?', '?', '?', '?', '?', '?', '?', '?', 
-    '?', '?', '?', '?', '?', '?', '?', '?', 
-    '?', '?', '?', '?', '?', '?', '?', '?', 
-];
-static mut SHIFT_PRESSED: bool = false;
-static mut CAPS_LOCK_PRESSED: bool = false;
-
-// TODO: Эта клавиатура с самого начала в основном файле
-//       стоит попробовать перенести в drivers/ наверное
-pub fn handle_input(scancode: u8) {
-    unsafe {
-        match scancode {
-            0x3A => {
-                CAPS_LOCK_PRESSED = !CAPS_LOCK_PRESSED;
-            }
-            0x2A | 0x36 => {
-                SHIFT_PRESSED = true;
-            }
-            0xAA | 0xB6 => {
-                SHIFT_PRESSED = false;
-            }
-            0x0E => {
-                if INPUT_BUFFER_INDEX > 0 {
-                    INPUT_BUFFER_INDEX -= 1;
-                    WRITER.lock().delete_char();
-                }
-            }
-            0x1C => {
-                if INPUT_BUFFER_INDEX > 0 {
-                    let input_str = core::str::from_utf8(&INPUT_BUFFER[0..INPUT_BUFFER_INDEX])
-                        .unwrap_or("")
-                        .trim();
-
-                    handle_command(input_str.as_bytes());
-
-                    INPUT_BUFFER_INDEX = 0;
-                }
-
-                print!(("\n>> "), fg: Color::LightGreen);
-            }
-            _ => {
-                if scancode < 128 {
-                    let character = if SHIFT_PRESSED {
-                        SCANCODE_TABLE_SHIFT[scancode as usize]
-                    } else {
-                        SCANCODE_TABLE[scancode as usize]
-                    };
-
-                    if character.is_ascii_alphabetic() {
-                        if CAPS_LOCK_PRESSED {
-                            INPUT_BUFFER[INPUT_BUFFER_INDEX] = character.to_ascii_uppercase() as u8;
-                        } else {
-                            INPUT_BUFFER[INPUT_BUFFER_INDEX] = character as u8;
-                        }
-                    } else {
-                        INPUT_BUFFER[INPUT_BUFFER_INDEX] = character as u8;
-                    }
-
-                    if character != '?' && INPUT_BUFFER_INDEX < INPUT_BUFFER_SIZE {
-                        INPUT_BUFFER_INDEX += 1;
-                        let char_to_print = if CAPS_LOCK_PRESSED {
-                            character.to_ascii_uppercase()
-                        } else {
-                            character
-                        };
-                        print!(("{}", char_to_print), fg: Color::LightGray);
-                    }
-
-                    if scancode == 0x2A || scancode == 0x36 {
-                        return; 
-                    }
-
-                    SHIFT_PRESSED = false;
-                }
-            }
-        }
-         
-    }
-}
-
-
 #[no_mangle]
-pub extern "C" fn rust_main() -> ! {
+pub extern "C" fn rust_main(multiboot_info: usize) -> ! {
     vga_buffer::clear_screen();
+    vga_buffer::load_font(&vga_buffer::CP866_8X16);
+    vga_buffer::enable_cursor();
+    // До первого print, чтобы весь журнал загрузки попал на COM1
+    let serial = drivers::serial::init();
     
     print!(("\nWelcome to Mini Rust OS 1.0\n"), fg: Color::LightBlue);
+
+    print!(("Initializing serial port... "), fg: Color::White);
+    match serial {
+        Ok(()) => print!(("OK\n"), fg: Color::LightGreen),
+        Err(e) => print!(("FAILED: {}\n", e), fg: Color::Yellow),
+    }
     
+    print!(("Loading GDT and TSS... "), fg: Color::White);
+    match gdt::init() {
+        Ok(()) => print!(("OK\n"), fg: Color::LightGreen),
+        Err(e) => print!(("FAILED: {}\n", e), fg: Color::Red),
+    }
+
+    print!(("Loading IDT... "), fg: Color::White);
+    interrupts::init();
+    print!(("OK\n"), fg: Color::LightGreen);
+
+    print!(("Initializing memory... "), fg: Color::White);
+    match multiboot::init(multiboot_info).ok_or("no multiboot2 info").and_then(|info| memory::init(&info)) {
+        Ok(stats) => print!(("OK ({} MiB free)\n", (stats.free as u64 * memory::FRAME_SIZE) >> 20), fg: Color::LightGreen),
+        Err(e) => print!(("FAILED: {}\n", e), fg: Color::Red),
+    }
+
+    print!(("Initializing heap... "), fg: Color::White);
+    match allocator::init() {
+        Ok(()) => print!(("OK ({} KiB)\n", allocator::stats().mapped >> 10), fg: Color::LightGreen),
+        Err(e) => print!(("FAILED: {}\n", e), fg: Color::Red),
+    }
+
     print!(("Initializing PIC... "), fg: Color::White);
     match drivers::pic::init() {
         Ok(_) => print!(("OK\n"), fg: Color::LightGreen),
@@ -182,21 +100,62 @@ pub extern "C" fn rust_main() -> ! {
     } else {
         print!(("FAILED\n"), fg: Color::Red);
     }
+
+    drivers::pic::mask_all();
+
+    print!(("Initializing APIC... "), fg: Color::White);
+    match drivers::apic::init() {
+        Ok(summary) => print!(("OK (LAPIC {}, {} I/O APIC, {} IRQs routed)\n", summary.local_id, summary.io_apics, summary.routed), fg: Color::LightGreen),
+        Err(e) => print!(("{}, using 8259 PIC\n", e), fg: Color::Yellow),
+    }
+
+    print!(("Initializing timer... "), fg: Color::White);
+    match drivers::pit::init(drivers::pit::DEFAULT_FREQUENCY) {
+        Ok(()) => print!(("OK ({} Hz)\n", drivers::pit::frequency()), fg: Color::LightGreen),
+        Err(e) => print!(("FAILED: {}\n", e), fg: Color::Red),
+    }
+
+    print!(("Calibrating TSC... "), fg: Color::White);
+    match drivers::tsc::init() {
+        Ok(()) => print!(("OK ({} MHz{})\n", drivers::tsc::frequency() / 1_000_000,
+            if drivers::tsc::is_invariant() { ", invariant" } else { "" }), fg: Color::LightGreen),
+        Err(e) => print!(("FAILED: {}\n", e), fg: Color::Red),
+    }
+
+    print!(("Reading RTC... "), fg: Color::White);
+    match drivers::rtc::init() {
+        Ok(now) => print!(("OK ({})\n", now), fg: Color::LightGreen),
+        Err(e) => print!(("FAILED: {}\n", e), fg: Color::Yellow),
+    }
+
+    print!(("Initializing keyboard... "), fg: Color::White);
+    keyboard::init();
+    print!(("OK\n"), fg: Color::LightGreen);
+
+    drivers::serial::enable_interrupts();
+
+    commands::init();
+
+    x86_64::instructions::interrupts::enable();
     
     print!(("\nType 'help' for a list of commands\n"), fg: Color::LightGray);
-    print!(("\n>> "), fg: Color::LightGreen);
+    shell::print_prompt();
     
-    let mut last_scancode: u8 = 0;
-
     loop {
-        let scancode = unsafe { read_keyboard_input() };
-
-        if scancode != last_scancode {
-            last_scancode = scancode;
-
-            if scancode != 0 {
-                handle_input(scancode);
-            }
+        while let Some(event) = keyboard::read_event() {
+            shell::handle_key(event);
+        }
+        while let Some(byte) = serial::read_byte() {
+            shell::handle_serial_byte(byte);
+        }
+        shell::clock::update();
+
+        // Проверка и hlt без окна между ними, иначе IRQ может прийти до hlt и мы уснём с непустой очередью
+        x86_64::instructions::interrupts::disable();
+        if keyboard::has_input() || serial::has_input() {
+            x86_64::instructions::interrupts::enable();
+        } else {
+            x86_64::instructions::interrupts::enable_and_hlt();
         }
     }
 }
@@ -204,6 +163,9 @@ pub extern "C" fn rust_main() -> ! {
 #[panic_handler]
 fn panic(info: &PanicInfo) -> ! {
     use core::fmt::Write;
+    // Сначала COM1: экран может быть заблокирован тем, кто упал
+    let _ = write!(drivers::serial::panic_writer(), "\n\nKernel panic: {}\n", info);
+
     let _lock = vga_buffer::WRITER_LOCK.lock();
     let mut writer = vga_buffer::WRITER.lock();
     
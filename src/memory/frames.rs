@@ -0,0 +1,145 @@
+//! Распределитель физических кадров по 4 КиБ на битовой карте.
+//!
+//! Бит на каждый кадр ниже `MAX_MEMORY`, единица — кадр свободен. Так карта
+//! из одних нулей оказывается в `.bss` и не раздувает образ ядра.
+
+use x86_64::structures::paging::{FrameAllocator, FrameDeallocator, PhysFrame, Size4KiB};
+use x86_64::PhysAddr;
+use crate::multiboot::{MemoryRegion, RegionKind};
+
+pub const FRAME_SIZE: u64 = 4096;
+
+// Выше 4 ГиБ память пока не используем
+const MAX_MEMORY: u64 = 4 << 30;
+const MAX_FRAMES: usize = (MAX_MEMORY / FRAME_SIZE) as usize;
+const WORDS: usize = MAX_FRAMES / 64;
+
+#[derive(Debug, Clone, Copy)]
+pub struct FrameStats {
+    /// Кадры, которые карта памяти отметила доступными.
+    pub usable: usize,
+    pub free: usize,
+    /// Байты в зарезервированных, ACPI и сбойных областях.
+    pub reserved_bytes: u64,
+    /// Доступная память выше `MAX_MEMORY`, которая не учитывается.
+    pub ignored_bytes: u64,
+}
+
+pub struct BitmapFrameAllocator {
+    bitmap: [u64; WORDS],
+    usable: usize,
+    free: usize,
+    reserved_bytes: u64,
+    ignored_bytes: u64,
+    // Слово, с которого начинается поиск: всё до него занято
+    next_word: usize,
+}
+
+impl BitmapFrameAllocator {
+    pub(super) const fn new() -> Self {
+        Self {
+            bitmap: [0; WORDS],
+            usable: 0,
+            free: 0,
+            reserved_bytes: 0,
+            ignored_bytes: 0,
+            next_word: 0,
+        }
+    }
+
+    fn is_free(&self, frame: usize) -> bool {
+        self.bitmap[frame / 64] & (1 << (frame % 64)) != 0
+    }
+
+    fn set_free(&mut self, frame: usize) {
+        self.bitmap[frame / 64] |= 1 << (frame % 64);
+    }
+
+    fn set_used(&mut self, frame: usize) {
+        self.bitmap[frame / 64] &= !(1 << (frame % 64));
+    }
+
+    /// Номера кадров, целиком лежащих в `[start, end)`.
+    fn inner_frames(start: u64, end: u64) -> core::ops::Range<usize> {
+        let first = start.div_ceil(FRAME_SIZE).min(MAX_FRAMES as u64);
+        let last = (end / FRAME_SIZE).min(MAX_FRAMES as u64);
+        first as usize..last.max(first) as usize
+    }
+
+    /// Номера кадров, хотя бы частично задетых `[start, end)`.
+    fn touched_frames(start: u64, end: u64) -> core::ops::Range<usize> {
+        let first = (start / FRAME_SIZE).min(MAX_FRAMES as u64);
+        let last = end.div_ceil(FRAME_SIZE).min(MAX_FRAMES as u64);
+        first as usize..last.max(first) as usize
+    }
+
+    pub fn add_region(&mut self, region: MemoryRegion) {
+        if region.kind != RegionKind::Available {
+            self.reserved_bytes += region.length;
+            return;
+        }
+
+        if region.end() > MAX_MEMORY {
+            self.ignored_bytes += region.end() - region.start.max(MAX_MEMORY);
+        }
+        // Области в карте могут перекрываться, второй раз кадр не считаем
+        for frame in Self::inner_frames(region.start, region.end()) {
+            if !self.is_free(frame) {
+                self.set_free(frame);
+                self.usable += 1;
+                self.free += 1;
+            }
+        }
+    }
+
+    /// Исключает `[start, end)` из выдачи; несвободные кадры не меняются.
+    pub fn reserve(&mut self, start: u64, end: u64) {
+        for frame in Self::touched_frames(start, end) {
+            if self.is_free(frame) {
+                self.set_used(frame);
+                self.free -= 1;
+            }
+        }
+    }
+
+    pub fn stats(&self) -> FrameStats {
+        FrameStats {
+            usable: self.usable,
+            free: self.free,
+            reserved_bytes: self.reserved_bytes,
+            ignored_bytes: self.ignored_bytes,
+        }
+    }
+
+    pub fn allocate(&mut self) -> Option<PhysFrame> {
+        let word = (self.next_word..WORDS).find(|&word| self.bitmap[word] != 0)?;
+        self.next_word = word;
+        let frame = word * 64 + self.bitmap[word].trailing_zeros() as usize;
+        self.set_used(frame);
+        self.free -= 1;
+        Some(PhysFrame::containing_address(PhysAddr::new(frame as u64 * FRAME_SIZE)))
+    }
+
+    /// Возвращает кадр, выданный `allocate`. Повторное освобождение игнорируется.
+    pub fn deallocate(&mut self, frame: PhysFrame) {
+        let index = (frame.start_address().as_u64() / FRAME_SIZE) as usize;
+        if index >= MAX_FRAMES || self.is_free(index) {
+            return;
+        }
+        self.set_free(index);
+        self.free += 1;
+        self.next_word = self.next_word.min(index / 64);
+    }
+}
+
+unsafe impl FrameAllocator<Size4KiB> for BitmapFrameAllocator {
+    fn allocate_frame(&mut self) -> Option<PhysFrame> {
+        self.allocate()
+    }
+}
+
+impl FrameDeallocator<Size4KiB> for BitmapFrameAllocator {
+    unsafe fn deallocate_frame(&mut self, frame: PhysFrame) {
+        self.deallocate(frame);
+    }
+}
@@ -0,0 +1,75 @@
+//! Память: учёт физических кадров по карте от загрузчика и таблицы страниц.
+
+use core::ptr::addr_of;
+use x86_64::structures::paging::{Page, Size4KiB};
+use x86_64::{PhysAddr, VirtAddr};
+use crate::multiboot::BootInfo;
+use crate::spin::SpinMutex;
+
+mod frames;
+pub mod paging;
+
+pub use frames::{BitmapFrameAllocator, FrameStats, FRAME_SIZE};
+
+extern "C" {
+    // Границы образа ядра из linker.ld
+    static kernel_start: u8;
+    static kernel_end: u8;
+    // Стек ядра из boot.nasm: страница-сторож и сам стек до stack_top
+    static stack_guard: u8;
+    static stack_top: u8;
+}
+
+// Нижний мегабайт не трогаем: там BDA, EBDA, видеопамять и BIOS
+const LOW_MEMORY_END: u64 = 0x10_0000;
+
+pub static FRAME_ALLOCATOR: SpinMutex<BitmapFrameAllocator> = SpinMutex::new(BitmapFrameAllocator::new());
+
+/// Границы образа ядра в физической памяти, конец не включается.
+pub fn kernel_range() -> (PhysAddr, PhysAddr) {
+    (
+        PhysAddr::new(addr_of!(kernel_start) as u64),
+        PhysAddr::new(addr_of!(kernel_end) as u64),
+    )
+}
+
+/// Страница под стеком ядра, которая после `init` не отображена.
+pub fn stack_guard_page() -> VirtAddr {
+    VirtAddr::new(addr_of!(stack_guard) as u64)
+}
+
+/// Стек ядра, конец не включается.
+pub fn kernel_stack() -> (VirtAddr, VirtAddr) {
+    (stack_guard_page() + FRAME_SIZE, VirtAddr::new(addr_of!(stack_top) as u64))
+}
+
+/// Попадает ли адрес на страницу-сторож, то есть переполнился ли стек.
+pub fn is_stack_guard(addr: u64) -> bool {
+    let guard = stack_guard_page().as_u64();
+    (guard..guard + FRAME_SIZE).contains(&addr)
+}
+
+pub fn init(boot_info: &BootInfo) -> Result<FrameStats, &'static str> {
+    let memory_map = boot_info.memory_map().ok_or("no memory map from the bootloader")?;
+    let mut allocator = FRAME_ALLOCATOR.lock();
+
+    for region in memory_map {
+        allocator.add_region(region);
+    }
+
+    allocator.reserve(0, LOW_MEMORY_END);
+    let (start, end) = kernel_range();
+    allocator.reserve(start.as_u64(), end.as_u64());
+    allocator.reserve(boot_info.start() as u64, boot_info.end() as u64);
+    for module in boot_info.modules() {
+        allocator.reserve(module.start, module.end);
+    }
+    let stats = allocator.stats();
+    // paging сам захватывает распределитель кадров
+    drop(allocator);
+
+    // Кадр сторожа остаётся в образе ядра, освобождать его не нужно
+    paging::unmap(Page::<Size4KiB>::containing_address(stack_guard_page()))
+        .map_err(|_| "cannot unmap the stack guard page")?;
+    Ok(stats)
+}
@@ -0,0 +1,443 @@
+//! Отображение виртуальных страниц на физические кадры.
+//!
+//! `boot.nasm` отображает первый гигабайт один к одному страницами по 2 МиБ,
+//! поэтому таблицы страниц читаются и пишутся прямо по физическому адресу
+//! (`OffsetPageTable` со смещением 0). Новые таблицы берутся только из этого
+//! гигабайта. Если 4 КиБ страница попадает внутрь большой, большая страница
+//! сначала делится на 512 обычных с теми же флагами.
+
+use core::fmt;
+use core::sync::atomic::{AtomicU64, Ordering};
+use x86_64::instructions::tlb;
+use x86_64::registers::control::Cr3;
+use x86_64::structures::paging::mapper::{
+    FlagUpdateError, MapToError, UnmapError,
+};
+use x86_64::structures::paging::{
+    FrameAllocator, Mapper, OffsetPageTable, Page, PageSize, PageTable, PageTableFlags,
+    PhysFrame, Size2MiB, Size4KiB,
+};
+use x86_64::{PhysAddr, VirtAddr};
+use crate::spin::SpinMutex;
+use super::{BitmapFrameAllocator, FRAME_ALLOCATOR};
+
+/// Граница памяти, отображённой один к одному при загрузке.
+pub const IDENTITY_LIMIT: u64 = 1 << 30;
+
+// Окно для регистров устройств, которые лежат выше IDENTITY_LIMIT
+const MMIO_START: u64 = 0x5555_0000_0000;
+const MMIO_SIZE: u64 = 1 << 30;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PagingError {
+    FrameAllocationFailed,
+    AlreadyMapped(PhysAddr),
+    NotMapped,
+    /// Адрес внутри страницы на 1 ГиБ, их мы не делим.
+    GiantPage,
+    MmioSpaceExhausted,
+}
+
+impl fmt::Display for PagingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PagingError::FrameAllocationFailed => write!(f, "Out of page table frames"),
+            PagingError::AlreadyMapped(frame) => write!(f, "Page already mapped to 0x{:X}", frame.as_u64()),
+            PagingError::NotMapped => write!(f, "Page is not mapped"),
+            PagingError::GiantPage => write!(f, "Address is inside a 1 GiB page"),
+            PagingError::MmioSpaceExhausted => write!(f, "MMIO window is full"),
+        }
+    }
+}
+
+impl<S: PageSize> From<MapToError<S>> for PagingError {
+    fn from(error: MapToError<S>) -> Self {
+        match error {
+            MapToError::FrameAllocationFailed => PagingError::FrameAllocationFailed,
+            MapToError::ParentEntryHugePage => PagingError::GiantPage,
+            MapToError::PageAlreadyMapped(frame) => PagingError::AlreadyMapped(frame.start_address()),
+        }
+    }
+}
+
+impl From<UnmapError> for PagingError {
+    fn from(error: UnmapError) -> Self {
+        match error {
+            UnmapError::ParentEntryHugePage => PagingError::GiantPage,
+            UnmapError::PageNotMapped | UnmapError::InvalidFrameAddress(_) => PagingError::NotMapped,
+        }
+    }
+}
+
+impl From<FlagUpdateError> for PagingError {
+    fn from(error: FlagUpdateError) -> Self {
+        match error {
+            FlagUpdateError::ParentEntryHugePage => PagingError::GiantPage,
+            FlagUpdateError::PageNotMapped => PagingError::NotMapped,
+        }
+    }
+}
+
+/// Куда отображён адрес.
+#[derive(Debug, Clone, Copy)]
+pub struct Mapping {
+    pub phys: PhysAddr,
+    pub page_size: u64,
+    pub flags: PageTableFlags,
+}
+
+/// Кадры для новых таблиц: только из первого гигабайта, иначе до таблицы
+/// не добраться по физическому адресу.
+struct TableFrames<'a>(&'a mut BitmapFrameAllocator);
+
+unsafe impl FrameAllocator<Size4KiB> for TableFrames<'_> {
+    fn allocate_frame(&mut self) -> Option<PhysFrame> {
+        // Кадры выдаются по возрастанию, так что выше границы — значит ниже свободных нет
+        let frame = self.0.allocate()?;
+        if frame.start_address().as_u64() >= IDENTITY_LIMIT {
+            self.0.deallocate(frame);
+            return None;
+        }
+        Some(frame)
+    }
+}
+
+// Порядок захвата: сначала PAGING, потом FRAME_ALLOCATOR
+static PAGING: SpinMutex<()> = SpinMutex::new(());
+static NEXT_MMIO: AtomicU64 = AtomicU64::new(MMIO_START);
+
+unsafe fn active_p4() -> &'static mut PageTable {
+    let (frame, _) = Cr3::read();
+    &mut *(frame.start_address().as_u64() as *mut PageTable)
+}
+
+unsafe fn active_table() -> OffsetPageTable<'static> {
+    OffsetPageTable::new(active_p4(), VirtAddr::new(0))
+}
+
+/// Выполняет `f` под блокировкой таблиц страниц; если операция упёрлась
+/// в большую страницу, делит её и пробует ещё раз.
+fn with_split<R, E>(
+    addr: VirtAddr,
+    mut f: impl FnMut(&mut OffsetPageTable<'static>, &mut TableFrames) -> Result<R, E>,
+    is_huge: impl Fn(&E) -> bool,
+) -> Result<R, PagingError>
+where
+    PagingError: From<E>,
+{
+    let _paging = PAGING.lock();
+    let mut allocator = FRAME_ALLOCATOR.lock();
+    let mut frames = TableFrames(&mut allocator);
+
+    match f(unsafe { &mut active_table() }, &mut frames) {
+        Err(error) if is_huge(&error) => {
+            split_huge_page(addr, &mut frames)?;
+            Ok(f(unsafe { &mut active_table() }, &mut frames)?)
+        }
+        result => Ok(result?),
+    }
+}
+
+/// Делит страницу на 2 МиБ, в которую попадает `addr`, на 4 КиБ страницы.
+fn split_huge_page(addr: VirtAddr, frames: &mut TableFrames) -> Result<(), PagingError> {
+    let page = Page::<Size4KiB>::containing_address(addr);
+    let p4 = unsafe { active_p4() };
+
+    let p4_entry = &p4[page.p4_index()];
+    if p4_entry.is_unused() {
+        return Err(PagingError::NotMapped);
+    }
+    let p3 = unsafe { &mut *(p4_entry.addr().as_u64() as *mut PageTable) };
+
+    let p3_entry = &p3[page.p3_index()];
+    if p3_entry.is_unused() {
+        return Err(PagingError::NotMapped);
+    }
+    if p3_entry.flags().contains(PageTableFlags::HUGE_PAGE) {
+        return Err(PagingError::GiantPage);
+    }
+    let p2 = unsafe { &mut *(p3_entry.addr().as_u64() as *mut PageTable) };
+
+    let entry = &mut p2[page.p2_index()];
+    if !entry.flags().contains(PageTableFlags::HUGE_PAGE) {
+        return Ok(());
+    }
+
+    let frame = frames.allocate_frame().ok_or(PagingError::FrameAllocationFailed)?;
+    let table = unsafe { &mut *(frame.start_address().as_u64() as *mut PageTable) };
+    let base = entry.addr();
+    let flags = entry.flags() & !PageTableFlags::HUGE_PAGE;
+    for (i, small) in table.iter_mut().enumerate() {
+        small.set_addr(base + i as u64 * Size4KiB::SIZE, flags);
+    }
+    entry.set_addr(frame.start_address(), flags);
+
+    // Одного invlpg достаточно, чтобы выбросить запись о большой странице
+    tlb::flush(Page::<Size2MiB>::containing_address(addr).start_address());
+    Ok(())
+}
+
+pub fn map<S: PageSize>(page: Page<S>, frame: PhysFrame<S>, flags: PageTableFlags) -> Result<(), PagingError>
+where
+    OffsetPageTable<'static>: Mapper<S>,
+{
+    let flags = flags | PageTableFlags::PRESENT;
+    with_split(
+        page.start_address(),
+        |mapper, frames| unsafe { mapper.map_to(page, frame, flags, frames) }.map(|flush| flush.flush()),
+        |error| matches!(error, MapToError::ParentEntryHugePage),
+    )
+}
+
+/// Снимает отображение и возвращает кадр; освобождать его — дело вызывающего.
+pub fn unmap<S: PageSize>(page: Page<S>) -> Result<PhysFrame<S>, PagingError>
+where
+    OffsetPageTable<'static>: Mapper<S>,
+{
+    with_split(
+        page.start_address(),
+        |mapper, _| {
+            mapper.unmap(page).map(|(frame, flush)| {
+                flush.flush();
+                frame
+            })
+        },
+        |error| matches!(error, UnmapError::ParentEntryHugePage),
+    )
+}
+
+/// Заменяет флаги страницы, `PRESENT` остаётся выставленным.
+#[allow(dead_code)]
+pub fn protect<S: PageSize>(page: Page<S>, flags: PageTableFlags) -> Result<(), PagingError>
+where
+    OffsetPageTable<'static>: Mapper<S>,
+{
+    let flags = flags | PageTableFlags::PRESENT;
+    with_split(
+        page.start_address(),
+        |mapper, _| unsafe { mapper.update_flags(page, flags) }.map(|flush| flush.flush()),
+        |error| matches!(error, FlagUpdateError::ParentEntryHugePage),
+    )
+}
+
+/// Почему к адресу нельзя обратиться.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessError {
+    NonCanonical,
+    /// Нет записи на уровне `level`: 4 — PML4, 1 — таблица страниц.
+    NotPresent { level: u8 },
+    /// Запись без WRITABLE на уровне `level`.
+    ReadOnly { level: u8 },
+}
+
+impl AccessError {
+    pub fn reason(self) -> &'static str {
+        match self {
+            AccessError::NonCanonical => "address is not canonical",
+            AccessError::NotPresent { level: 4 } => "not mapped: no PML4 entry",
+            AccessError::NotPresent { level: 3 } => "not mapped: no PDPT entry",
+            AccessError::NotPresent { level: 2 } => "not mapped: no page directory entry",
+            AccessError::NotPresent { .. } => "page is not present",
+            AccessError::ReadOnly { level: 1 } => "page is read-only",
+            AccessError::ReadOnly { .. } => "page is read-only (set by a higher-level table)",
+        }
+    }
+}
+
+impl fmt::Display for AccessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.reason())
+    }
+}
+
+/// Проходит по таблицам от CR3, как это делает процессор. В `flags`
+/// собираются действующие права: WRITABLE, только если он стоит на всех
+/// уровнях, и NO_EXECUTE, если он стоит хоть на одном.
+pub fn walk(addr: u64) -> Result<Mapping, AccessError> {
+    walk_levels(addr).map(|(mapping, _)| mapping)
+}
+
+/// То же, что `walk`, плюс самый верхний уровень, на котором снят WRITABLE.
+fn walk_levels(addr: u64) -> Result<(Mapping, Option<u8>), AccessError> {
+    let mut flags = PageTableFlags::PRESENT | PageTableFlags::WRITABLE;
+    let mut read_only = None;
+
+    for entry in table_entries(addr)?.into_iter().flatten() {
+        if !entry.flags.contains(PageTableFlags::PRESENT) {
+            return Err(AccessError::NotPresent { level: entry.level });
+        }
+        if !entry.flags.contains(PageTableFlags::WRITABLE) {
+            flags.remove(PageTableFlags::WRITABLE);
+            read_only.get_or_insert(entry.level);
+        }
+        flags |= entry.flags & PageTableFlags::NO_EXECUTE;
+
+        if is_leaf(entry.level, entry.flags) {
+            let page_size = level_page_size(entry.level);
+            let leaf = entry.flags & !(PageTableFlags::WRITABLE | PageTableFlags::NO_EXECUTE);
+            let mapping = Mapping {
+                phys: entry.addr + (addr & (page_size - 1)),
+                page_size,
+                flags: leaf | flags,
+            };
+            return Ok((mapping, read_only));
+        }
+    }
+    unreachable!("table_entries stops at a leaf or a missing entry")
+}
+
+/// Конечная ли запись: на уровне 1 всегда, на 3 и 2 — большая страница.
+fn is_leaf(level: u8, flags: PageTableFlags) -> bool {
+    level == 1 || (level != 4 && flags.contains(PageTableFlags::HUGE_PAGE))
+}
+
+/// Сколько памяти покрывает запись уровня `level`.
+fn level_page_size(level: u8) -> u64 {
+    Size4KiB::SIZE << (9 * (level as u64 - 1))
+}
+
+/// Проверяет, что `len` байт с `start` можно прочитать или, если `write`,
+/// записать, не вызвав исключения.
+pub fn check_access(start: u64, len: u64, write: bool) -> Result<(), AccessError> {
+    let last = start.checked_add(len.max(1) - 1).ok_or(AccessError::NonCanonical)?;
+    let mut addr = start;
+    loop {
+        let (mapping, read_only) = walk_levels(addr)?;
+        if let (true, Some(level)) = (write, read_only) {
+            return Err(AccessError::ReadOnly { level });
+        }
+        // Следующая страница после той, в которую попал addr
+        let next = (addr | (mapping.page_size - 1)).checked_add(1);
+        match next {
+            Some(next) if next <= last => addr = next,
+            _ => return Ok(()),
+        }
+    }
+}
+
+/// Запись таблицы страниц на одном уровне обхода.
+#[derive(Debug, Clone, Copy)]
+pub struct TableEntry {
+    /// 4 — PML4, 3 — PDPT, 2 — каталог страниц, 1 — таблица страниц.
+    pub level: u8,
+    pub index: usize,
+    /// Физический адрес таблицы, в которой лежит запись.
+    pub table: PhysAddr,
+    pub addr: PhysAddr,
+    pub flags: PageTableFlags,
+}
+
+/// Записи, через которые процессор проходит для `addr`, от PML4 вниз.
+/// Обход кончается на конечной или отсутствующей записи, дальше `None`.
+pub fn table_entries(addr: u64) -> Result<[Option<TableEntry>; 4], AccessError> {
+    let addr = VirtAddr::try_new(addr).map_err(|_| AccessError::NonCanonical)?;
+    let page = Page::<Size4KiB>::containing_address(addr);
+    let indices = [page.p4_index(), page.p3_index(), page.p2_index(), page.p1_index()];
+
+    let _paging = PAGING.lock();
+    let mut entries = [None; 4];
+    let mut table: &PageTable = unsafe { active_p4() };
+    for (depth, &index) in indices.iter().enumerate() {
+        let level = 4 - depth as u8;
+        let entry = &table[index];
+        entries[depth] = Some(TableEntry {
+            level,
+            index: usize::from(index),
+            table: PhysAddr::new(table as *const PageTable as u64),
+            addr: entry.addr(),
+            flags: entry.flags(),
+        });
+        let flags = entry.flags();
+        if !flags.contains(PageTableFlags::PRESENT) || is_leaf(level, flags) {
+            break;
+        }
+        table = unsafe { &*(entry.addr().as_u64() as *const PageTable) };
+    }
+    Ok(entries)
+}
+
+/// Передаёт `f` отображённые страницы от `from` по возрастанию адреса:
+/// начало, кадр, размер страницы и флаги конечной записи. Если `f` вернула
+/// `false`, обход прекращается. Таблицы заблокированы на всё время обхода,
+/// поэтому `f` не должна ждать: длинный обход прерывают и продолжают с
+/// адреса, на котором остановились.
+pub fn for_each_mapping(from: VirtAddr, mut f: impl FnMut(VirtAddr, PhysAddr, u64, PageTableFlags) -> bool) {
+    let _paging = PAGING.lock();
+    let p4 = unsafe { active_p4() };
+    visit(p4, 4, 0, from.as_u64(), &mut f);
+}
+
+fn visit(
+    table: &PageTable,
+    level: u8,
+    base: u64,
+    from: u64,
+    f: &mut impl FnMut(VirtAddr, PhysAddr, u64, PageTableFlags) -> bool,
+) -> bool {
+    let page_size = level_page_size(level);
+    for (index, entry) in table.iter().enumerate() {
+        let flags = entry.flags();
+        if !flags.contains(PageTableFlags::PRESENT) {
+            continue;
+        }
+        // new_truncate продолжает знаковый бит для верхней половины
+        let start = VirtAddr::new_truncate(base + index as u64 * page_size);
+        // Последний байт, а не конец: у последней записи PML4 конец переполнится
+        if start.as_u64() + (page_size - 1) < from {
+            continue;
+        }
+        let keep_going = if is_leaf(level, flags) {
+            f(start, entry.addr(), page_size, flags)
+        } else {
+            let next = unsafe { &*(entry.addr().as_u64() as *const PageTable) };
+            visit(next, level - 1, start.as_u64(), from, f)
+        };
+        if !keep_going {
+            return false;
+        }
+    }
+    true
+}
+
+#[allow(dead_code)]
+pub fn flush(addr: VirtAddr) {
+    tlb::flush(addr);
+}
+
+#[allow(dead_code)]
+pub fn flush_all() {
+    tlb::flush_all();
+}
+
+/// Резервирует в окне MMIO `size` байт адресов, ничего не отображая.
+/// Окно не возвращается, так что резервировать стоит один раз.
+pub fn reserve_window(size: u64) -> Result<VirtAddr, PagingError> {
+    let size = size.next_multiple_of(Size4KiB::SIZE);
+    let start = NEXT_MMIO.fetch_add(size, Ordering::Relaxed);
+    if start + size > MMIO_START + MMIO_SIZE {
+        return Err(PagingError::MmioSpaceExhausted);
+    }
+    Ok(VirtAddr::new(start))
+}
+
+/// Отображает страницы с `phys` по `phys + size` подряд начиная с
+/// выровненного адреса `virt`.
+pub fn map_range(virt: VirtAddr, phys: PhysAddr, size: u64, flags: PageTableFlags) -> Result<(), PagingError> {
+    let first = PhysFrame::<Size4KiB>::containing_address(phys);
+    let last = PhysFrame::<Size4KiB>::containing_address(phys + size.max(1) - 1u64);
+    for (i, frame) in PhysFrame::range_inclusive(first, last).enumerate() {
+        let page = Page::<Size4KiB>::containing_address(virt + i as u64 * Size4KiB::SIZE);
+        map(page, frame, flags)?;
+    }
+    Ok(())
+}
+
+/// Отображает регистры устройства в окно MMIO без кэширования.
+/// Возвращает виртуальный адрес, соответствующий `phys`.
+pub fn map_mmio(phys: PhysAddr, size: u64) -> Result<VirtAddr, PagingError> {
+    let offset = phys.as_u64() % Size4KiB::SIZE;
+    let start = reserve_window(offset + size.max(1))?;
+    let flags = PageTableFlags::WRITABLE | PageTableFlags::NO_CACHE | PageTableFlags::WRITE_THROUGH;
+    map_range(start, phys, size, flags)?;
+    Ok(start + offset)
+}
@@ -0,0 +1,230 @@
+//! Информация, которую загрузчик передаёт по multiboot2.
+//!
+//! Структура лежит в памяти загрузчика: фиксированный заголовок, за ним
+//! теги, каждый выровнен на 8 байт. Заканчивается список тегом типа 0.
+//! Ядро её только читает, а распределитель кадров не отдаёт эту память.
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+const TAG_END: u32 = 0;
+const TAG_BOOTLOADER_NAME: u32 = 2;
+const TAG_MODULE: u32 = 3;
+const TAG_MEMORY_MAP: u32 = 6;
+const TAG_ACPI_OLD_RSDP: u32 = 14;
+const TAG_ACPI_NEW_RSDP: u32 = 15;
+
+const HEADER_SIZE: usize = 8;
+const TAG_ALIGN: usize = 8;
+
+#[derive(Debug, Clone, Copy)]
+pub struct BootInfo {
+    start: usize,
+    size: usize,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Tag {
+    pub kind: u32,
+    pub size: u32,
+    /// Адрес начала тега, вместе с полями `kind` и `size`.
+    pub addr: usize,
+}
+
+impl Tag {
+    /// Поле тега по смещению от его начала.
+    unsafe fn read<T: Copy>(&self, offset: usize) -> T {
+        ((self.addr + offset) as *const T).read_unaligned()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegionKind {
+    Available,
+    Reserved,
+    AcpiReclaimable,
+    AcpiNvs,
+    BadMemory,
+}
+
+impl RegionKind {
+    fn from_u32(value: u32) -> RegionKind {
+        match value {
+            1 => RegionKind::Available,
+            3 => RegionKind::AcpiReclaimable,
+            4 => RegionKind::AcpiNvs,
+            5 => RegionKind::BadMemory,
+            _ => RegionKind::Reserved,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            RegionKind::Available => "available",
+            RegionKind::Reserved => "reserved",
+            RegionKind::AcpiReclaimable => "ACPI reclaimable",
+            RegionKind::AcpiNvs => "ACPI NVS",
+            RegionKind::BadMemory => "bad memory",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct MemoryRegion {
+    pub start: u64,
+    pub length: u64,
+    pub kind: RegionKind,
+}
+
+impl MemoryRegion {
+    pub fn end(&self) -> u64 {
+        self.start.saturating_add(self.length)
+    }
+}
+
+/// Загруженный вместе с ядром модуль; `end` не входит в модуль.
+#[derive(Debug, Clone, Copy)]
+pub struct Module {
+    pub start: u64,
+    pub end: u64,
+}
+
+impl BootInfo {
+    /// # Safety
+    /// `addr` должен указывать на структуру multiboot2 в отображённой памяти.
+    pub unsafe fn load(addr: usize) -> Option<BootInfo> {
+        if addr == 0 || !addr.is_multiple_of(TAG_ALIGN) {
+            return None;
+        }
+        let size = (addr as *const u32).read() as usize;
+        if size < HEADER_SIZE {
+            return None;
+        }
+        Some(BootInfo { start: addr, size })
+    }
+
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    pub fn end(&self) -> usize {
+        self.start + self.size
+    }
+
+    pub fn tags(&self) -> Tags {
+        Tags {
+            next: self.start + HEADER_SIZE,
+            end: self.end(),
+        }
+    }
+
+    fn find(&self, kind: u32) -> Option<Tag> {
+        self.tags().find(|tag| tag.kind == kind)
+    }
+
+    pub fn memory_map(&self) -> Option<MemoryMap> {
+        let tag = self.find(TAG_MEMORY_MAP)?;
+        let entry_size = unsafe { tag.read::<u32>(8) } as usize;
+        // Запись не короче base + length + type
+        if entry_size < 20 {
+            return None;
+        }
+        Some(MemoryMap {
+            next: tag.addr + 16,
+            end: tag.addr + tag.size as usize,
+            entry_size,
+        })
+    }
+
+    pub fn modules(&self) -> impl Iterator<Item = Module> {
+        self.tags()
+            .filter(|tag| tag.kind == TAG_MODULE)
+            .map(|tag| unsafe {
+                Module {
+                    start: tag.read::<u32>(8) as u64,
+                    end: tag.read::<u32>(12) as u64,
+                }
+            })
+    }
+
+    /// Адрес копии RSDP, которую загрузчик кладёт в тег. Тег ACPI 2.0
+    /// предпочтительнее: в нём есть адрес XSDT.
+    pub fn rsdp(&self) -> Option<usize> {
+        let tag = self.find(TAG_ACPI_NEW_RSDP).or_else(|| self.find(TAG_ACPI_OLD_RSDP))?;
+        Some(tag.addr + 8)
+    }
+
+    pub fn bootloader_name(&self) -> Option<&'static str> {
+        let tag = self.find(TAG_BOOTLOADER_NAME)?;
+        let bytes = unsafe {
+            core::slice::from_raw_parts((tag.addr + 8) as *const u8, tag.size as usize - 8)
+        };
+        let len = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+        core::str::from_utf8(&bytes[..len]).ok()
+    }
+}
+
+pub struct Tags {
+    next: usize,
+    end: usize,
+}
+
+impl Iterator for Tags {
+    type Item = Tag;
+
+    fn next(&mut self) -> Option<Tag> {
+        if self.next + 8 > self.end {
+            return None;
+        }
+        let tag = unsafe {
+            Tag {
+                kind: (self.next as *const u32).read(),
+                size: ((self.next + 4) as *const u32).read(),
+                addr: self.next,
+            }
+        };
+        if tag.kind == TAG_END || tag.size < 8 {
+            self.next = self.end;
+            return None;
+        }
+        self.next = (self.next + tag.size as usize).next_multiple_of(TAG_ALIGN);
+        Some(tag)
+    }
+}
+
+pub struct MemoryMap {
+    next: usize,
+    end: usize,
+    entry_size: usize,
+}
+
+impl Iterator for MemoryMap {
+    type Item = MemoryRegion;
+
+    fn next(&mut self) -> Option<MemoryRegion> {
+        if self.next + self.entry_size > self.end {
+            return None;
+        }
+        let entry = self.next as *const u8;
+        self.next += self.entry_size;
+        unsafe {
+            Some(MemoryRegion {
+                start: (entry as *const u64).read_unaligned(),
+                length: (entry.add(8) as *const u64).read_unaligned(),
+                kind: RegionKind::from_u32((entry.add(16) as *const u32).read_unaligned()),
+            })
+        }
+    }
+}
+
+static BOOT_INFO: AtomicUsize = AtomicUsize::new(0);
+
+/// Запоминает адрес из EBX, который `boot.nasm` передаёт в `rust_main`.
+pub fn init(addr: usize) -> Option<BootInfo> {
+    let info = unsafe { BootInfo::load(addr)? };
+    BOOT_INFO.store(addr, Ordering::Relaxed);
+    Some(info)
+}
+
+pub fn info() -> Option<BootInfo> {
+    unsafe { BootInfo::load(BOOT_INFO.load(Ordering::Relaxed)) }
+}
@@ -0,0 +1,60 @@
+//! Часы в правом верхнем углу экрана.
+//!
+//! Главный цикл просыпается на каждом тике таймера и зовёт `update`; RTC
+//! читается только когда сменилась секунда. Прокрутка экрана стирает часы,
+//! но через секунду они рисуются снова.
+
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use crate::drivers::{pit, rtc};
+use crate::vga_buffer::{self, Color};
+
+// "YYYY-MM-DD HH:MM:SS" и по пробелу с краёв
+const WIDTH: usize = 21;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static LAST_SECOND: AtomicU64 = AtomicU64::new(u64::MAX);
+
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+    LAST_SECOND.store(u64::MAX, Ordering::Relaxed);
+    if enabled {
+        update();
+    } else {
+        vga_buffer::write_top_right(core::str::from_utf8(&[b' '; WIDTH]).unwrap_or(""), Color::LightGray, Color::Black);
+    }
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+pub fn update() {
+    if !is_enabled() {
+        return;
+    }
+    let second = pit::uptime_ms() / 1000;
+    if LAST_SECOND.swap(second, Ordering::Relaxed) == second {
+        return;
+    }
+
+    let now = rtc::read();
+    let mut text = *b" 0000-00-00 00:00:00 ";
+    put_digits(&mut text[1..5], now.year as u32);
+    put_digits(&mut text[6..8], now.month as u32);
+    put_digits(&mut text[9..11], now.day as u32);
+    put_digits(&mut text[12..14], now.hour as u32);
+    put_digits(&mut text[15..17], now.minute as u32);
+    put_digits(&mut text[18..20], now.second as u32);
+
+    if let Ok(text) = core::str::from_utf8(&text) {
+        vga_buffer::write_top_right(text, Color::Black, Color::LightGray);
+    }
+}
+
+/// Десятичные цифры `value` с ведущими нулями на всю ширину `out`.
+fn put_digits(out: &mut [u8], mut value: u32) {
+    for digit in out.iter_mut().rev() {
+        *digit = b'0' + (value % 10) as u8;
+        value /= 10;
+    }
+}
@@ -0,0 +1,212 @@
+//! Редактирование строки ввода.
+//!
+//! Редактор не знает, куда выводится строка: всё рисование идёт через
+//! `Console`, которому нужно уметь печатать текст и двигать курсор.
+//! После каждой правки перерисовывается только хвост строки от места
+//! изменения, остаток старой строки затирается пробелами.
+
+pub const MAX_LINE: usize = 128;
+
+pub trait Console {
+    fn write_str(&mut self, s: &str);
+    fn cursor_back(&mut self, count: usize);
+    fn cursor_forward(&mut self, count: usize);
+}
+
+/// Кодирует символы в UTF-8, возвращает длину в байтах.
+/// В `out` должно помещаться `MAX_LINE * 4` байт.
+pub fn encode(chars: &[char], out: &mut [u8]) -> usize {
+    let mut len = 0;
+    for &c in chars {
+        len += c.encode_utf8(&mut out[len..]).len();
+    }
+    len
+}
+
+#[derive(Clone, Copy)]
+pub struct Line {
+    chars: [char; MAX_LINE],
+    len: usize,
+}
+
+impl Line {
+    pub const fn new() -> Self {
+        Self {
+            chars: ['\0'; MAX_LINE],
+            len: 0,
+        }
+    }
+
+    pub fn chars(&self) -> &[char] {
+        &self.chars[..self.len]
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn encode(&self, out: &mut [u8]) -> usize {
+        encode(self.chars(), out)
+    }
+}
+
+impl PartialEq for Line {
+    fn eq(&self, other: &Self) -> bool {
+        self.chars() == other.chars()
+    }
+}
+
+pub struct LineEditor {
+    line: Line,
+    cursor: usize,
+}
+
+impl LineEditor {
+    pub const fn new() -> Self {
+        Self {
+            line: Line::new(),
+            cursor: 0,
+        }
+    }
+
+    pub fn line(&self) -> &Line {
+        &self.line
+    }
+
+    /// Символы до курсора.
+    pub fn before_cursor(&self) -> &[char] {
+        &self.line.chars[..self.cursor]
+    }
+
+    /// Забывает строку, не трогая экран: вызывается после Enter.
+    pub fn clear(&mut self) {
+        self.line.len = 0;
+        self.cursor = 0;
+    }
+
+    pub fn insert(&mut self, c: char, console: &mut dyn Console) -> bool {
+        let len = self.line.len;
+        if len == MAX_LINE {
+            return false;
+        }
+
+        self.line.chars.copy_within(self.cursor..len, self.cursor + 1);
+        self.line.chars[self.cursor] = c;
+        self.line.len += 1;
+
+        let from = self.cursor;
+        self.cursor += 1;
+        self.redraw(from, len, console);
+        true
+    }
+
+    pub fn backspace(&mut self, console: &mut dyn Console) {
+        if self.cursor > 0 {
+            self.remove(self.cursor - 1, self.cursor, console);
+        }
+    }
+
+    pub fn delete(&mut self, console: &mut dyn Console) {
+        if self.cursor < self.line.len {
+            self.remove(self.cursor, self.cursor + 1, console);
+        }
+    }
+
+    pub fn move_left(&mut self, console: &mut dyn Console) {
+        if self.cursor > 0 {
+            self.move_to(self.cursor - 1, console);
+        }
+    }
+
+    pub fn move_right(&mut self, console: &mut dyn Console) {
+        if self.cursor < self.line.len {
+            self.move_to(self.cursor + 1, console);
+        }
+    }
+
+    pub fn move_home(&mut self, console: &mut dyn Console) {
+        self.move_to(0, console);
+    }
+
+    pub fn move_end(&mut self, console: &mut dyn Console) {
+        self.move_to(self.line.len, console);
+    }
+
+    /// Ctrl+U
+    pub fn kill_to_start(&mut self, console: &mut dyn Console) {
+        self.remove(0, self.cursor, console);
+    }
+
+    /// Ctrl+K
+    pub fn kill_to_end(&mut self, console: &mut dyn Console) {
+        self.remove(self.cursor, self.line.len, console);
+    }
+
+    /// Ctrl+W: удаляет слово перед курсором вместе с пробелами после него.
+    pub fn kill_word(&mut self, console: &mut dyn Console) {
+        let chars = self.line.chars();
+        let mut start = self.cursor;
+        while start > 0 && chars[start - 1].is_whitespace() {
+            start -= 1;
+        }
+        while start > 0 && !chars[start - 1].is_whitespace() {
+            start -= 1;
+        }
+        self.remove(start, self.cursor, console);
+    }
+
+    /// Печатает строку заново, например после списка вариантов дополнения.
+    /// Курсор консоли должен стоять в начале строки.
+    pub fn reprint(&self, console: &mut dyn Console) {
+        self.redraw(0, self.line.len, console);
+    }
+
+    /// Подменяет строку целиком (история), курсор встаёт в конец.
+    pub fn replace(&mut self, line: &Line, console: &mut dyn Console) {
+        let old_len = self.line.len;
+        self.move_to(0, console);
+        self.line = *line;
+        self.cursor = self.line.len;
+        self.redraw(0, old_len, console);
+    }
+
+    fn remove(&mut self, start: usize, end: usize, console: &mut dyn Console) {
+        if start == end {
+            return;
+        }
+
+        let old_len = self.line.len;
+        self.move_to(start, console);
+        self.line.chars.copy_within(end..old_len, start);
+        self.line.len -= end - start;
+        self.redraw(start, old_len, console);
+    }
+
+    fn move_to(&mut self, position: usize, console: &mut dyn Console) {
+        if position < self.cursor {
+            console.cursor_back(self.cursor - position);
+        } else if position > self.cursor {
+            console.cursor_forward(position - self.cursor);
+        }
+        self.cursor = position;
+    }
+
+    /// Перерисовывает строку с позиции `from`, на которой сейчас стоит
+    /// курсор консоли, и возвращает курсор на `self.cursor`.
+    fn redraw(&self, from: usize, old_len: usize, console: &mut dyn Console) {
+        let mut buf = [0u8; 4];
+        for c in &self.line.chars[from..self.line.len] {
+            console.write_str(c.encode_utf8(&mut buf));
+        }
+
+        let tail = old_len.saturating_sub(self.line.len);
+        for _ in 0..tail {
+            console.write_str(" ");
+        }
+
+        let back = self.line.len + tail - self.cursor;
+        if back > 0 {
+            console.cursor_back(back);
+        }
+    }
+}
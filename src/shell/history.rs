@@ -0,0 +1,39 @@
+use super::editor::Line;
+
+pub const HISTORY_SIZE: usize = 16;
+
+/// Кольцо последних команд, новые затирают самые старые.
+pub struct History {
+    entries: [Line; HISTORY_SIZE],
+    next: usize,
+    count: usize,
+}
+
+impl History {
+    pub const fn new() -> Self {
+        Self {
+            entries: [Line::new(); HISTORY_SIZE],
+            next: 0,
+            count: 0,
+        }
+    }
+
+    /// Пустые строки и повтор предыдущей команды не запоминаются.
+    pub fn push(&mut self, line: &Line) {
+        if line.is_empty() || self.get(0) == Some(line) {
+            return;
+        }
+
+        self.entries[self.next] = *line;
+        self.next = (self.next + 1) % HISTORY_SIZE;
+        self.count = (self.count + 1).min(HISTORY_SIZE);
+    }
+
+    /// `age` 0 — последняя команда, 1 — предпоследняя и так далее.
+    pub fn get(&self, age: usize) -> Option<&Line> {
+        if age >= self.count {
+            return None;
+        }
+        Some(&self.entries[(self.next + HISTORY_SIZE - 1 - age) % HISTORY_SIZE])
+    }
+}
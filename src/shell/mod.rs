@@ -0,0 +1,289 @@
+use core::ptr::{addr_of, addr_of_mut};
+use crate::allocator;
+use crate::commands;
+use crate::console::{with_output, Output};
+use crate::drivers::keyboard::{KeyCode, KeyEvent};
+use crate::drivers::serial;
+use crate::handle_command::handle_command;
+use crate::interrupts;
+use crate::print;
+use crate::spin::SpinMutex;
+use crate::vga_buffer::{self, Color};
+
+pub mod clock;
+mod editor;
+mod history;
+mod vt100;
+
+use editor::{Console, Line, LineEditor, MAX_LINE};
+use history::History;
+use vt100::Decoder;
+
+pub const PROMPT: &str = ">> ";
+
+// Больше слов в строке всё равно не передаётся команде
+const MAX_WORDS: usize = 16;
+
+/// Ввод, уже не зависящий от того, откуда он пришёл.
+pub enum Input {
+    Key(KeyEvent),
+    /// Готовый символ: терминал сам учитывает раскладку.
+    Char(char),
+}
+
+struct Shell {
+    editor: LineEditor,
+    history: History,
+    // Недописанная строка, пока листаем историю
+    draft: Line,
+    browsing: Option<usize>,
+    // Второй Tab подряд показывает варианты
+    after_tab: bool,
+    scratch: [u8; MAX_LINE * 4],
+}
+
+impl Shell {
+    const fn new() -> Self {
+        Self {
+            editor: LineEditor::new(),
+            history: History::new(),
+            draft: Line::new(),
+            browsing: None,
+            after_tab: false,
+            scratch: [0; MAX_LINE * 4],
+        }
+    }
+
+    /// Возвращает длину команды в `COMMAND_BUFFER`, если нажат Enter.
+    fn process(&mut self, input: Input, console: &mut dyn Console) -> Option<usize> {
+        let event = match input {
+            Input::Key(event) => event,
+            Input::Char(character) => {
+                self.after_tab = false;
+                self.editor.insert(character, console);
+                return None;
+            }
+        };
+
+        let ctrl = event.modifiers.ctrl();
+        let after_tab = core::mem::replace(&mut self.after_tab, event.key == KeyCode::Tab);
+        let editor = &mut self.editor;
+
+        match event.key {
+            KeyCode::Enter | KeyCode::KeypadEnter => {
+                // Вывод команды должен начаться после строки, а не с места курсора
+                editor.move_end(console);
+                return Some(self.submit());
+            }
+            KeyCode::Backspace => editor.backspace(console),
+            KeyCode::Delete => editor.delete(console),
+            KeyCode::ArrowLeft => editor.move_left(console),
+            KeyCode::ArrowRight => editor.move_right(console),
+            KeyCode::Home => editor.move_home(console),
+            KeyCode::End => editor.move_end(console),
+            KeyCode::Tab => self.complete(after_tab, console),
+            KeyCode::ArrowUp => self.history_older(console),
+            KeyCode::ArrowDown => self.history_newer(console),
+            KeyCode::A if ctrl => editor.move_home(console),
+            KeyCode::E if ctrl => editor.move_end(console),
+            KeyCode::U if ctrl => editor.kill_to_start(console),
+            KeyCode::K if ctrl => editor.kill_to_end(console),
+            KeyCode::W if ctrl => editor.kill_word(console),
+            _ => {
+                if let Some(character) = event.to_char() {
+                    editor.insert(character, console);
+                }
+            }
+        }
+        None
+    }
+
+    fn submit(&mut self) -> usize {
+        let len = self.editor.line().encode(unsafe { &mut *addr_of_mut!(COMMAND_BUFFER) });
+        self.history.push(self.editor.line());
+        self.editor.clear();
+        self.browsing = None;
+        len
+    }
+
+    fn complete(&mut self, list: bool, console: &mut dyn Console) {
+        let len = editor::encode(self.editor.before_cursor(), &mut self.scratch);
+        let text = core::str::from_utf8(&self.scratch[..len]).unwrap_or("");
+
+        let mut words = [""; MAX_WORDS];
+        let mut count = 0;
+        for word in text.split_whitespace() {
+            if count == MAX_WORDS {
+                return;
+            }
+            words[count] = word;
+            count += 1;
+        }
+
+        // Дополняется последнее слово, если после него ещё нет пробела
+        let partial = if count > 0 && !text.ends_with(char::is_whitespace) {
+            count -= 1;
+            words[count]
+        } else {
+            ""
+        };
+        let words = &words[..count];
+
+        let mut matches = 0;
+        let mut first = "";
+        let mut common = 0;
+        commands::complete(words, &mut |candidate| {
+            if !candidate.starts_with(partial) {
+                return;
+            }
+            if matches == 0 {
+                first = candidate;
+                common = candidate.len();
+            } else {
+                common = common.min(common_prefix(first, candidate));
+            }
+            matches += 1;
+        });
+
+        if matches == 0 {
+            return;
+        }
+
+        if matches == 1 || common > partial.len() {
+            for c in first[partial.len()..common].chars() {
+                self.editor.insert(c, console);
+            }
+            if matches == 1 {
+                self.editor.insert(' ', console);
+            }
+        } else if list {
+            console.write_str("\n");
+            commands::complete(words, &mut |candidate| {
+                if candidate.starts_with(partial) {
+                    console.write_str(candidate);
+                    console.write_str("  ");
+                }
+            });
+            print_prompt();
+            self.editor.reprint(console);
+        }
+    }
+
+    fn history_older(&mut self, console: &mut dyn Console) {
+        let age = self.browsing.map_or(0, |age| age + 1);
+        let Some(entry) = self.history.get(age) else {
+            return;
+        };
+
+        if self.browsing.is_none() {
+            self.draft = *self.editor.line();
+        }
+        self.browsing = Some(age);
+        self.editor.replace(entry, console);
+    }
+
+    fn history_newer(&mut self, console: &mut dyn Console) {
+        match self.browsing {
+            None => {}
+            Some(0) => {
+                self.browsing = None;
+                self.editor.replace(&self.draft, console);
+            }
+            Some(age) => {
+                if let Some(entry) = self.history.get(age - 1) {
+                    self.browsing = Some(age - 1);
+                    self.editor.replace(entry, console);
+                }
+            }
+        }
+    }
+}
+
+// У каждой консоли своя строка и своя история
+static SCREEN_SHELL: SpinMutex<Shell> = SpinMutex::new(Shell::new());
+static SERIAL_SHELL: SpinMutex<Shell> = SpinMutex::new(Shell::new());
+static SERIAL_DECODER: SpinMutex<Decoder> = SpinMutex::new(Decoder::new());
+
+// Команда выполняется без блокировки SHELL: если в ней случится исключение,
+// мы выпрыгнем из run_guarded, не отпустив захваченные замки
+static mut COMMAND_BUFFER: [u8; MAX_LINE * 4] = [0; MAX_LINE * 4];
+
+struct VgaConsole;
+
+impl Console for VgaConsole {
+    fn write_str(&mut self, s: &str) {
+        print!(("{}", s), fg: Color::LightGray);
+    }
+
+    fn cursor_back(&mut self, count: usize) {
+        vga_buffer::cursor_back(count);
+    }
+
+    fn cursor_forward(&mut self, count: usize) {
+        vga_buffer::cursor_forward(count);
+    }
+}
+
+struct SerialConsole;
+
+impl Console for SerialConsole {
+    fn write_str(&mut self, s: &str) {
+        serial::write_fmt(format_args!("{}", s));
+    }
+
+    // `ESC [ 0 D` сдвигает на одну позицию, поэтому ноль пропускаем
+    fn cursor_back(&mut self, count: usize) {
+        if count > 0 {
+            serial::write_fmt(format_args!("\x1b[{}D", count));
+        }
+    }
+
+    fn cursor_forward(&mut self, count: usize) {
+        if count > 0 {
+            serial::write_fmt(format_args!("\x1b[{}C", count));
+        }
+    }
+}
+
+/// Длина общего начала двух строк в байтах, по границам символов.
+fn common_prefix(a: &str, b: &str) -> usize {
+    a.char_indices()
+        .zip(b.chars())
+        .find(|&((_, x), y)| x != y)
+        .map_or(a.len().min(b.len()), |((i, _), _)| i)
+}
+
+pub fn print_prompt() {
+    print!(("\n{}", PROMPT), fg: Color::LightGreen);
+}
+
+pub fn handle_key(event: KeyEvent) {
+    if !event.pressed {
+        return;
+    }
+
+    dispatch(&SCREEN_SHELL, Input::Key(event), &mut VgaConsole, Output::Screen);
+}
+
+pub fn handle_serial_byte(byte: u8) {
+    let input = SERIAL_DECODER.lock().process(byte);
+    if let Some(input) = input {
+        dispatch(&SERIAL_SHELL, input, &mut SerialConsole, Output::Serial);
+    }
+}
+
+/// Всё, что печатают редактор и команда, уходит на консоль, с которой пришёл ввод.
+fn dispatch(shell: &SpinMutex<Shell>, input: Input, console: &mut dyn Console, output: Output) {
+    with_output(output, || {
+        let len = match shell.lock().process(input, console) {
+            Some(len) => len,
+            None => return,
+        };
+
+        let command = unsafe { &(&*addr_of!(COMMAND_BUFFER))[..len] };
+        // Метку кучи ставит сама команда; если она упадёт, восстановление
+        // пропустит её сброс, поэтому сбрасываем здесь, снаружи run_guarded
+        let _ = allocator::with_tag("kernel", || interrupts::run_guarded(|| handle_command(command)));
+        print_prompt();
+    });
+}
@@ -0,0 +1,160 @@
+//! Разбор ввода с терминала на COM1.
+//!
+//! Терминал присылает печатные символы в UTF-8, управляющие байты для
+//! Ctrl+буква и escape-последовательности VT100 для стрелок и Home/End.
+//! Клавиши превращаются в те же `KeyEvent`, что приходят с клавиатуры.
+
+use crate::drivers::keyboard::{KeyCode, KeyEvent, Modifiers};
+use super::Input;
+
+const ESCAPE: u8 = 0x1B;
+const DELETE: u8 = 0x7F;
+const BACKSPACE: u8 = 0x08;
+
+// Ctrl+A присылается как 0x01, Ctrl+Z как 0x1A
+const CTRL_LETTERS: [KeyCode; 26] = [
+    KeyCode::A, KeyCode::B, KeyCode::C, KeyCode::D, KeyCode::E, KeyCode::F, KeyCode::G,
+    KeyCode::H, KeyCode::I, KeyCode::J, KeyCode::K, KeyCode::L, KeyCode::M, KeyCode::N,
+    KeyCode::O, KeyCode::P, KeyCode::Q, KeyCode::R, KeyCode::S, KeyCode::T, KeyCode::U,
+    KeyCode::V, KeyCode::W, KeyCode::X, KeyCode::Y, KeyCode::Z,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Ground,
+    Escape,
+    /// `ESC [` и накопленный числовой параметр.
+    Csi(u8),
+    /// `ESC O`: так часть терминалов шлёт стрелки и Home/End.
+    Ss3,
+    /// Середина многобайтового символа UTF-8: сколько байт ещё ждём.
+    Utf8(u8),
+}
+
+pub struct Decoder {
+    state: State,
+    code_point: u32,
+    // Enter приходит как `\r`, `\r\n` или `\n`
+    after_cr: bool,
+}
+
+impl Decoder {
+    pub const fn new() -> Self {
+        Self {
+            state: State::Ground,
+            code_point: 0,
+            after_cr: false,
+        }
+    }
+
+    pub fn process(&mut self, byte: u8) -> Option<Input> {
+        let after_cr = core::mem::replace(&mut self.after_cr, byte == b'\r');
+
+        match self.state {
+            State::Ground => self.ground(byte, after_cr),
+            State::Escape => {
+                self.state = match byte {
+                    b'[' => State::Csi(0),
+                    b'O' => State::Ss3,
+                    _ => State::Ground,
+                };
+                None
+            }
+            State::Csi(param) => match byte {
+                b'0'..=b'9' => {
+                    self.state = State::Csi(param.saturating_mul(10).saturating_add(byte - b'0'));
+                    None
+                }
+                // Модификаторы вроде `ESC [ 1 ; 5 C` не различаем
+                b';' => None,
+                b'~' => {
+                    self.state = State::Ground;
+                    match param {
+                        1 | 7 => key(KeyCode::Home),
+                        3 => key(KeyCode::Delete),
+                        4 | 8 => key(KeyCode::End),
+                        _ => None,
+                    }
+                }
+                0x40..=0x7E => {
+                    self.state = State::Ground;
+                    cursor_key(byte)
+                }
+                _ => None,
+            },
+            State::Ss3 => {
+                self.state = State::Ground;
+                cursor_key(byte)
+            }
+            State::Utf8(remaining) => {
+                if byte & 0xC0 != 0x80 {
+                    // Оборванная последовательность: начинаем заново с этого байта
+                    self.state = State::Ground;
+                    return self.ground(byte, after_cr);
+                }
+                self.code_point = self.code_point << 6 | (byte & 0x3F) as u32;
+                if remaining > 1 {
+                    self.state = State::Utf8(remaining - 1);
+                    return None;
+                }
+                self.state = State::Ground;
+                char::from_u32(self.code_point).map(Input::Char)
+            }
+        }
+    }
+
+    fn ground(&mut self, byte: u8, after_cr: bool) -> Option<Input> {
+        match byte {
+            ESCAPE => {
+                self.state = State::Escape;
+                None
+            }
+            b'\r' => key(KeyCode::Enter),
+            b'\n' if after_cr => None,
+            b'\n' => key(KeyCode::Enter),
+            b'\t' => key(KeyCode::Tab),
+            DELETE | BACKSPACE => key(KeyCode::Backspace),
+            0x01..=0x1A => {
+                let mut modifiers = Modifiers::new();
+                modifiers.left_ctrl = true;
+                Some(Input::Key(KeyEvent {
+                    key: CTRL_LETTERS[(byte - 1) as usize],
+                    modifiers,
+                    pressed: true,
+                }))
+            }
+            0x20..=0x7E => Some(Input::Char(byte as char)),
+            0xC0..=0xF7 => {
+                let (remaining, bits) = match byte {
+                    0xC0..=0xDF => (1, byte & 0x1F),
+                    0xE0..=0xEF => (2, byte & 0x0F),
+                    _ => (3, byte & 0x07),
+                };
+                self.code_point = bits as u32;
+                self.state = State::Utf8(remaining);
+                None
+            }
+            _ => None,
+        }
+    }
+}
+
+fn key(key: KeyCode) -> Option<Input> {
+    Some(Input::Key(KeyEvent {
+        key,
+        modifiers: Modifiers::new(),
+        pressed: true,
+    }))
+}
+
+fn cursor_key(byte: u8) -> Option<Input> {
+    match byte {
+        b'A' => key(KeyCode::ArrowUp),
+        b'B' => key(KeyCode::ArrowDown),
+        b'C' => key(KeyCode::ArrowRight),
+        b'D' => key(KeyCode::ArrowLeft),
+        b'H' => key(KeyCode::Home),
+        b'F' => key(KeyCode::End),
+        _ => None,
+    }
+}
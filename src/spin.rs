@@ -2,6 +2,15 @@ use core::sync::atomic::{AtomicUsize, Ordering};
 use core::cell::UnsafeCell;
 use core::ops::{Deref, DerefMut};
 
+// Сколько замков захвачено сейчас. Процессор один, так что это замки
+// прерванного кода: по счётчику восстановление после исключения узнаёт,
+// не бросит ли оно кого-то под замком
+static HELD: AtomicUsize = AtomicUsize::new(0);
+
+pub fn held_locks() -> usize {
+    HELD.load(Ordering::Relaxed)
+}
+
 pub struct Spinlock {
     locked: AtomicUsize,
 }
@@ -21,8 +30,15 @@ impl Spinlock {
         {
             core::hint::spin_loop();
         }
+        HELD.fetch_add(1, Ordering::Relaxed);
         SpinlockGuard { lock: self }
     }
+
+    pub unsafe fn force_unlock(&self) {
+        if self.locked.swap(0, Ordering::Release) != 0 {
+            HELD.fetch_sub(1, Ordering::Relaxed);
+        }
+    }
 }
 
 pub struct SpinlockGuard<'a> {
@@ -32,6 +48,7 @@ pub struct SpinlockGuard<'a> {
 impl Drop for SpinlockGuard<'_> {
     fn drop(&mut self) {
         self.lock.locked.store(0, Ordering::Release);
+        HELD.fetch_sub(1, Ordering::Relaxed);
     }
 }
 
@@ -57,6 +74,10 @@ impl<T> SpinMutex<T> {
             data: unsafe { &mut *self.data.get() },
         }
     }
+
+    pub unsafe fn force_unlock(&self) {
+        self.lock.force_unlock();
+    }
 }
 
 pub struct SpinMutexGuard<'a, T> {
@@ -0,0 +1,155 @@
+//! Загрузка глифов в знакогенератор VGA (битовая плоскость 2).
+//!
+//! В текстовом режиме плоскость 2 недоступна, поэтому на время записи
+//! sequencer и graphics controller переключаются на линейный доступ к ней
+//! по адресу 0xA0000, а потом возвращаются в исходное состояние.
+
+use x86_64::instructions::interrupts::without_interrupts;
+use x86_64::instructions::port::Port;
+
+use crate::spin::SpinMutex;
+use super::codepage::{self, CodePage};
+use super::glyphs;
+
+pub const GLYPH_HEIGHT: usize = 16;
+pub const GLYPH_COUNT: usize = 256;
+pub type Glyph = [u8; GLYPH_HEIGHT];
+
+pub struct Font {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub glyphs: &'static [Glyph; GLYPH_COUNT],
+    pub codepage: &'static CodePage,
+}
+
+pub static CP866_8X16: Font = Font {
+    name: "cp866",
+    description: "CP866 8x16, Cyrillic",
+    glyphs: &glyphs::CP866_8X16,
+    codepage: &codepage::CP866,
+};
+
+/// Копия шрифта из ПЗУ, снимается перед первой загрузкой своего шрифта.
+struct SavedFont {
+    glyphs: [Glyph; GLYPH_COUNT],
+    valid: bool,
+}
+
+// Лежит в static, а не на стеке: это 4 КиБ
+static BIOS_FONT: SpinMutex<SavedFont> = SpinMutex::new(SavedFont {
+    glyphs: [[0; GLYPH_HEIGHT]; GLYPH_COUNT],
+    valid: false,
+});
+
+const SEQ_INDEX: u16 = 0x3C4;
+const SEQ_DATA: u16 = 0x3C5;
+const GC_INDEX: u16 = 0x3CE;
+const GC_DATA: u16 = 0x3CF;
+
+const SEQ_MAP_MASK: u8 = 0x02;
+const SEQ_MEMORY_MODE: u8 = 0x04;
+const GC_READ_MAP: u8 = 0x04;
+const GC_MODE: u8 = 0x05;
+const GC_MISC: u8 = 0x06;
+
+const FONT_MEMORY: usize = 0xA0000;
+// Каждый символ занимает 32 байта, даже если шрифт ниже
+const GLYPH_STRIDE: usize = 32;
+
+struct SavedRegisters {
+    map_mask: u8,
+    memory_mode: u8,
+    read_map: u8,
+    mode: u8,
+    misc: u8,
+}
+
+unsafe fn read_register(index_port: u16, data_port: u16, index: u8) -> u8 {
+    Port::<u8>::new(index_port).write(index);
+    Port::<u8>::new(data_port).read()
+}
+
+unsafe fn write_register(index_port: u16, data_port: u16, index: u8, value: u8) {
+    Port::<u8>::new(index_port).write(index);
+    Port::<u8>::new(data_port).write(value);
+}
+
+unsafe fn open_font_plane() -> SavedRegisters {
+    let saved = SavedRegisters {
+        map_mask: read_register(SEQ_INDEX, SEQ_DATA, SEQ_MAP_MASK),
+        memory_mode: read_register(SEQ_INDEX, SEQ_DATA, SEQ_MEMORY_MODE),
+        read_map: read_register(GC_INDEX, GC_DATA, GC_READ_MAP),
+        mode: read_register(GC_INDEX, GC_DATA, GC_MODE),
+        misc: read_register(GC_INDEX, GC_DATA, GC_MISC),
+    };
+
+    // Пишем только в плоскость 2, последовательная адресация без odd/even
+    write_register(SEQ_INDEX, SEQ_DATA, SEQ_MAP_MASK, 0x04);
+    write_register(SEQ_INDEX, SEQ_DATA, SEQ_MEMORY_MODE, 0x07);
+    // Читаем тоже из плоскости 2, окно 0xA0000-0xAFFFF
+    write_register(GC_INDEX, GC_DATA, GC_READ_MAP, 0x02);
+    write_register(GC_INDEX, GC_DATA, GC_MODE, 0x00);
+    write_register(GC_INDEX, GC_DATA, GC_MISC, 0x04);
+
+    saved
+}
+
+unsafe fn close_font_plane(saved: SavedRegisters) {
+    write_register(SEQ_INDEX, SEQ_DATA, SEQ_MAP_MASK, saved.map_mask);
+    write_register(SEQ_INDEX, SEQ_DATA, SEQ_MEMORY_MODE, saved.memory_mode);
+    write_register(GC_INDEX, GC_DATA, GC_READ_MAP, saved.read_map);
+    write_register(GC_INDEX, GC_DATA, GC_MODE, saved.mode);
+    write_register(GC_INDEX, GC_DATA, GC_MISC, saved.misc);
+}
+
+fn write_glyphs(first: u8, glyphs: &[Glyph]) {
+    without_interrupts(|| unsafe {
+        let saved = open_font_plane();
+        let base = FONT_MEMORY as *mut u8;
+
+        for (i, glyph) in glyphs.iter().enumerate() {
+            let slot = base.add((first as usize + i) * GLYPH_STRIDE);
+            for (row, &bits) in glyph.iter().enumerate() {
+                slot.add(row).write_volatile(bits);
+            }
+        }
+
+        close_font_plane(saved);
+    });
+}
+
+fn read_glyphs(glyphs: &mut [Glyph]) {
+    without_interrupts(|| unsafe {
+        let saved = open_font_plane();
+        let base = FONT_MEMORY as *const u8;
+
+        for (i, glyph) in glyphs.iter_mut().enumerate() {
+            let slot = base.add(i * GLYPH_STRIDE);
+            for (row, bits) in glyph.iter_mut().enumerate() {
+                *bits = slot.add(row).read_volatile();
+            }
+        }
+
+        close_font_plane(saved);
+    });
+}
+
+/// Загружает шрифт в знакогенератор. Вызывать под `WRITER_LOCK`:
+/// пока плоскость 2 открыта, текстовый буфер по 0xB8000 недоступен.
+pub fn upload(font: &Font) {
+    let mut bios_font = BIOS_FONT.lock();
+    if !bios_font.valid {
+        read_glyphs(&mut bios_font.glyphs);
+        bios_font.valid = true;
+    }
+    write_glyphs(0, font.glyphs);
+}
+
+/// Возвращает шрифт BIOS. `false`, если его никто не заменял.
+pub fn restore_bios_font() -> bool {
+    let bios_font = BIOS_FONT.lock();
+    if bios_font.valid {
+        write_glyphs(0, &bios_font.glyphs);
+    }
+    bios_font.valid
+}
@@ -2,11 +2,17 @@
 
 use core::fmt;
 use volatile::Volatile;
+use x86_64::instructions::port::Port;
 use core::ptr::NonNull;
 use lazy_static::lazy_static;
 use crate::spin::SpinMutex;
 
+mod codepage;
+mod font;
+mod glyphs;
 mod modes;
+pub use codepage::CodePage;
+pub use font::{Font, CP866_8X16};
 pub use modes::{VideoMode, VideoState};
 
 #[allow(dead_code)]
@@ -83,14 +89,23 @@ struct ScreenChar {
 const DEFAULT_HEIGHT: usize = 25;
 const DEFAULT_WIDTH: usize = 80;
 
+const CRTC_INDEX: u16 = 0x3D4;
+const CRTC_DATA: u16 = 0x3D5;
+const CRTC_CURSOR_START: u8 = 0x0A;
+const CRTC_CURSOR_END: u8 = 0x0B;
+const CRTC_CURSOR_HIGH: u8 = 0x0E;
+const CRTC_CURSOR_LOW: u8 = 0x0F;
+
 pub(crate) struct Buffer {
     chars: [[Volatile<ScreenChar>; DEFAULT_WIDTH]; DEFAULT_HEIGHT],
 }
 
 pub struct Writer {
     column_position: usize,
+    row_position: usize,
     color_code: ColorCode,
     video_state: VideoState,
+    codepage: &'static CodePage,
 }
 
 unsafe impl Send for Writer {}
@@ -102,8 +117,10 @@ impl Writer {
         video_state.set_mode(VideoMode::Text80x25);
         Self {
             column_position: 0,
+            row_position: video_state.height - 1,
             color_code: ColorCode::new(Color::LightGreen, Color::Black),
             video_state,
+            codepage: &codepage::CP437,
         }
     }
     
@@ -114,31 +131,54 @@ impl Writer {
     pub fn set_video_mode(&mut self, mode: VideoMode) {
         self.video_state.set_mode(mode);
         self.column_position = 0;
+        self.row_position = self.video_state.height - 1;
     }
     
     pub fn write_byte(&mut self, byte: u8) {
         match byte {
             b'\n' => self.new_line(),
-            byte => {
-                if self.column_position >= self.video_state.width {
-                    self.new_line();
-                }
-                let row = self.video_state.height - 1;
-                let col = self.column_position;
-                let color_code = self.color_code;
-                
-                unsafe {
-                    self.video_state.buffer.as_mut().chars[row][col].write(ScreenChar {
-                        ascii_character: byte,
-                        color_code,
-                    });
-                }
-                self.column_position += 1;
+            byte => self.put_glyph(byte),
+        }
+    }
+
+    /// Выводит символ в кодировке загруженного шрифта, символы без глифа
+    /// заменяются на `codepage::REPLACEMENT`.
+    pub fn write_char(&mut self, c: char) {
+        match c {
+            '\n' => self.new_line(),
+            c => {
+                let byte = self.codepage.encode(c).unwrap_or(codepage::REPLACEMENT);
+                self.put_glyph(byte);
             }
         }
     }
 
+    // Управляющие коды в шрифте тоже заняты глифами, поэтому байт
+    // выводится как есть
+    fn put_glyph(&mut self, byte: u8) {
+        if self.column_position >= self.video_state.width {
+            self.new_line();
+        }
+        let row = self.row_position;
+        let col = self.column_position;
+        let color_code = self.color_code;
+
+        unsafe {
+            self.video_state.buffer.as_mut().chars[row][col].write(ScreenChar {
+                ascii_character: byte,
+                color_code,
+            });
+        }
+        self.column_position += 1;
+    }
+
     fn new_line(&mut self) {
+        self.column_position = 0;
+        if self.row_position + 1 < self.video_state.height {
+            self.row_position += 1;
+            return;
+        }
+
         for row in 1..self.video_state.height {
             for col in 0..self.video_state.width {
                 let character = unsafe { self.video_state.buffer.as_ref().chars[row][col].read() };
@@ -148,7 +188,6 @@ impl Writer {
             }
         }
         self.clear_row(self.video_state.height - 1);
-        self.column_position = 0;
     }
 
     fn clear_row(&mut self, row: usize) {
@@ -163,28 +202,67 @@ impl Writer {
         }
     }
 
-    pub fn delete_char(&mut self) {
-        if self.column_position > 0 {
-            self.column_position -= 1;
-            let row = self.video_state.height - 1;
-            let col = self.column_position;
-            
-            let blank = ScreenChar {
-                ascii_character: b' ',
-                color_code: self.color_code,
-            };
-            
+    /// Сдвигает позицию вывода на `count` клеток назад, с переходом на
+    /// предыдущие строки. Выше верхней строки экрана не уходит.
+    pub fn cursor_back(&mut self, count: usize) {
+        let width = self.video_state.width;
+        let offset = (self.row_position * width + self.column_position).saturating_sub(count);
+        self.row_position = offset / width;
+        self.column_position = offset % width;
+    }
+
+    /// Сдвигает позицию вывода вперёд по уже выведенному тексту. В конце
+    /// последней строки останавливается за краем, как после записи символа.
+    pub fn cursor_forward(&mut self, count: usize) {
+        let width = self.video_state.width;
+        let last_row = self.video_state.height - 1;
+        let offset = self.row_position * width + self.column_position + count;
+        if offset / width > last_row {
+            self.row_position = last_row;
+            self.column_position = (offset - last_row * width).min(width);
+        } else {
+            self.row_position = offset / width;
+            self.column_position = offset % width;
+        }
+    }
+
+    /// Ставит аппаратный курсор туда, куда попадёт следующий символ.
+    pub fn update_cursor(&self) {
+        let width = self.video_state.width;
+        let (row, col) = if self.column_position >= width {
+            (self.row_position + 1, 0)
+        } else {
+            (self.row_position, self.column_position)
+        };
+        // Курсор за нижним краем просто не виден, этого и надо
+        let position = (row * width + col) as u16;
+        unsafe {
+            let mut index = Port::<u8>::new(CRTC_INDEX);
+            let mut data = Port::<u8>::new(CRTC_DATA);
+            index.write(CRTC_CURSOR_LOW);
+            data.write(position as u8);
+            index.write(CRTC_CURSOR_HIGH);
+            data.write((position >> 8) as u8);
+        }
+    }
+
+    /// Пишет текст в верхнюю строку вплотную к правому краю, не трогая
+    /// позицию вывода. Текст, не влезший в строку, отбрасывается слева.
+    pub fn write_top_right(&mut self, text: &str, fg: Color, bg: Color) {
+        let color_code = ColorCode::new(fg, bg);
+        let width = self.video_state.width;
+        let count = text.chars().count().min(width);
+        for (i, c) in text.chars().skip(text.chars().count() - count).enumerate() {
+            let byte = self.codepage.encode(c).unwrap_or(codepage::REPLACEMENT);
             unsafe {
-                self.video_state.buffer.as_mut().chars[row][col].write(blank);
+                self.video_state.buffer.as_mut().chars[0][width - count + i].write(ScreenChar {
+                    ascii_character: byte,
+                    color_code,
+                });
             }
-        } else if self.video_state.height > 1 {
-            self.clear_row(self.video_state.height - 1);
-            self.column_position = self.video_state.width - 1;
-            
-            self.delete_char();
         }
     }
-    
+
     pub fn set_foreground_color(&mut self, color: Color) {
         self.color_code = ColorCode::new(color, self.color_code.background());
     }
@@ -198,13 +276,14 @@ impl Writer {
             self.clear_row(row);
         }
         self.column_position = 0;
+        self.row_position = self.video_state.height - 1;
     }
 }
 
 impl fmt::Write for Writer {
     fn write_str(&mut self, s: &str) -> fmt::Result {
-        for byte in s.bytes() {
-            self.write_byte(byte);
+        for c in s.chars() {
+            self.write_char(c);
         }
         Ok(())
     }
@@ -232,6 +311,66 @@ pub fn print(args: fmt::Arguments, fg: Option<Color>, bg: Option<Color>) {
     
     let _ = writer.write_fmt(args);
     writer.set_color(Color::LightGreen, Color::Black);
+    writer.update_cursor();
+}
+
+pub fn cursor_back(count: usize) {
+    let _lock = WRITER_LOCK.lock();
+    let mut writer = WRITER.lock();
+    writer.cursor_back(count);
+    writer.update_cursor();
+}
+
+pub fn cursor_forward(count: usize) {
+    let _lock = WRITER_LOCK.lock();
+    let mut writer = WRITER.lock();
+    writer.cursor_forward(count);
+    writer.update_cursor();
+}
+
+pub fn write_top_right(text: &str, fg: Color, bg: Color) {
+    let _lock = WRITER_LOCK.lock();
+    WRITER.lock().write_top_right(text, fg, bg);
+}
+
+/// Включает курсор-подчёркивание: загрузчик мог его спрятать.
+pub fn enable_cursor() {
+    unsafe {
+        let mut index = Port::<u8>::new(CRTC_INDEX);
+        let mut data = Port::<u8>::new(CRTC_DATA);
+        // Строки развёртки 14-15 из 16, бит 5 регистра 0x0A выключает курсор
+        index.write(CRTC_CURSOR_START);
+        data.write(14);
+        index.write(CRTC_CURSOR_END);
+        data.write(15);
+    }
+    WRITER.lock().update_cursor();
+}
+
+pub unsafe fn force_unlock() {
+    WRITER_LOCK.force_unlock();
+    WRITER.force_unlock();
+}
+
+pub static FONTS: [&Font; 1] = [&CP866_8X16];
+
+pub fn load_font(font: &'static Font) {
+    let _lock = WRITER_LOCK.lock();
+    font::upload(font);
+    WRITER.lock().codepage = font.codepage;
+}
+
+pub fn restore_bios_font() -> bool {
+    let _lock = WRITER_LOCK.lock();
+    let restored = font::restore_bios_font();
+    if restored {
+        WRITER.lock().codepage = &codepage::CP437;
+    }
+    restored
+}
+
+pub fn active_codepage() -> &'static CodePage {
+    WRITER.lock().codepage
 }
 
 pub fn clear_screen() {